@@ -0,0 +1,66 @@
+//! Reproduces the moderate-contention pattern from `mutex.rs`'s `lots_and_lots` unit test (a
+//! handful of threads hammering one shared counter) to justify `AdaptiveMutex`'s bounded
+//! try-lock-then-enqueue fast path against plain `Mutex::lock` under that workload.
+//!
+//! Run with `cargo bench --bench lots_and_lots --features adaptive`.
+
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+use std::thread;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use mcs::{AdaptiveMutex, Mutex, Slot};
+
+const ITERS: u32 = 1000;
+const CONCURRENCY: u32 = 3;
+
+fn plain_lots_and_lots() {
+    let lock = Arc::new(Mutex::new(0u32));
+    let (tx, rx) = channel();
+    for _ in 0..2 * CONCURRENCY {
+        let lock = lock.clone();
+        let tx = tx.clone();
+        thread::spawn(move || {
+            let mut slot = Slot::new();
+            for _ in 0..ITERS {
+                *lock.lock(&mut slot) += 1;
+            }
+            tx.send(()).unwrap();
+        });
+    }
+    drop(tx);
+    for _ in 0..2 * CONCURRENCY {
+        rx.recv().unwrap();
+    }
+}
+
+fn adaptive_lots_and_lots() {
+    let lock = Arc::new(AdaptiveMutex::new(0u32));
+    let (tx, rx) = channel();
+    for _ in 0..2 * CONCURRENCY {
+        let lock = lock.clone();
+        let tx = tx.clone();
+        thread::spawn(move || {
+            let mut slot = Slot::new();
+            for _ in 0..ITERS {
+                *lock.lock(&mut slot) += 1;
+            }
+            tx.send(()).unwrap();
+        });
+    }
+    drop(tx);
+    for _ in 0..2 * CONCURRENCY {
+        rx.recv().unwrap();
+    }
+}
+
+fn bench_lots_and_lots(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lots_and_lots");
+    group.bench_function("plain", |b| b.iter(plain_lots_and_lots));
+    group.bench_function("adaptive", |b| b.iter(adaptive_lots_and_lots));
+    group.finish();
+}
+
+criterion_group!(benches, bench_lots_and_lots);
+criterion_main!(benches);
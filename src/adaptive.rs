@@ -0,0 +1,244 @@
+//! A mutex that spins on a cheap test-and-set style fast path while uncontended, and falls back
+//! to the fair MCS queue once contention is detected.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "park-adaptive")]
+use core::hint;
+
+#[cfg(feature = "park-adaptive")]
+use std::sync::atomic::AtomicUsize;
+#[cfg(feature = "park-adaptive")]
+use std::thread;
+#[cfg(feature = "park-adaptive")]
+use std::time::Duration;
+
+use crate::mutex::{Guard, Mutex, Slot};
+use crate::pause::pause;
+use crate::reborrow::reborrow_mut;
+
+/// Number of failed fast-path attempts before a `lock` call gives up spinning and joins the MCS
+/// queue instead.
+const SPIN_ATTEMPTS: u32 = 32;
+
+/// Number of `core::hint::spin_loop` attempts `lock_adaptive` makes before escalating from
+/// spinning to `thread::yield_now`.
+#[cfg(feature = "park-adaptive")]
+const ADAPTIVE_SPIN_BURST: u32 = 64;
+
+/// Number of `thread::yield_now` attempts `lock_adaptive` makes before escalating from yielding
+/// to parking the thread.
+#[cfg(feature = "park-adaptive")]
+const ADAPTIVE_YIELD_BURST: u32 = 64;
+
+/// How long each `thread::park_timeout` call waits before `lock_adaptive` retries `try_lock`,
+/// once escalated to the parking stage.
+///
+/// A timeout, rather than an indefinite `thread::park`, is used because nothing here wires up an
+/// explicit unpark call from the releasing thread; instead this polls, trading a bit of latency
+/// for staying a self-contained addition on top of the existing non-blocking `try_lock` API
+/// rather than reaching into the MCS release path's handoff signal.
+#[cfg(feature = "park-adaptive")]
+const ADAPTIVE_PARK_TIMEOUT: Duration = Duration::from_micros(50);
+
+/// Counts how many times `lock_adaptive` has escalated all the way to the parking stage, so tests
+/// can observe that a long hold actually drives a waiter there.
+#[cfg(all(test, feature = "park-adaptive"))]
+static PARK_STAGE_ENTRIES: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(all(test, feature = "park-adaptive"))]
+fn park_stage_entries() -> usize {
+    PARK_STAGE_ENTRIES.load(Ordering::Relaxed)
+}
+
+/// Acquires `mutex`, escalating through three waiting strategies as contention persists.
+///
+/// A short burst of `core::hint::spin_loop` gives the best latency for locks held only briefly.
+/// If that doesn't succeed, a burst of `thread::yield_now` gives up the CPU to other runnable
+/// threads without fully parking, suiting moderate contention. If the lock is still held after
+/// that, the waiter parks itself (waking periodically to retry) rather than spending CPU at all,
+/// suiting long holds. `try_lock` is retried between every attempt at every stage, so this can
+/// return as soon as the lock becomes available rather than waiting out a whole stage.
+#[cfg(feature = "park-adaptive")]
+pub fn lock_adaptive<'a, T: ?Sized>(mutex: &'a Mutex<T>, slot: &'a mut Slot) -> Guard<'a, T> {
+    // `try_lock` ties its returned `Guard`'s lifetime to `slot`'s own, so retrying it across these
+    // stages needs reborrowing `slot` for that same `'a` on every attempt; see `reborrow_mut` for
+    // why that's sound despite the borrow checker not seeing it itself.
+    let slot: *mut Slot = slot;
+
+    for _ in 0..ADAPTIVE_SPIN_BURST {
+        if let Ok(guard) = mutex.try_lock(unsafe { reborrow_mut(slot) }) {
+            return guard;
+        }
+        hint::spin_loop();
+    }
+
+    for _ in 0..ADAPTIVE_YIELD_BURST {
+        if let Ok(guard) = mutex.try_lock(unsafe { reborrow_mut(slot) }) {
+            return guard;
+        }
+        thread::yield_now();
+    }
+
+    #[cfg(test)]
+    PARK_STAGE_ENTRIES.fetch_add(1, Ordering::Relaxed);
+
+    loop {
+        if let Ok(guard) = mutex.try_lock(unsafe { reborrow_mut(slot) }) {
+            return guard;
+        }
+        thread::park_timeout(ADAPTIVE_PARK_TIMEOUT);
+    }
+}
+
+/// A mutex that behaves like a simple test-and-set spinlock while uncontended, and promotes to
+/// the fair, queueing `Mutex` once contention is observed.
+///
+/// Plain MCS already has a cheap, single-CAS uncontended path (see `Mutex::try_lock`), but it
+/// still pays for `Slot` bookkeeping on every `lock` call. `AdaptiveMutex` skips that bookkeeping
+/// by spinning on `try_lock` directly for a bounded number of attempts, only joining the queue
+/// once spinning has failed repeatedly. This trades fairness for latency on the (assumed common)
+/// uncontended path, while still falling back to the fair, starvation-free queue under load.
+pub struct AdaptiveMutex<T: ?Sized> {
+    /// Set when a `lock` call's spinning fails and it has to join the queue, so calls made while
+    /// it is set skip straight past spinning; cleared again as soon as a queued acquisition
+    /// completes.
+    contended: AtomicBool,
+    inner: Mutex<T>
+}
+
+impl<T> AdaptiveMutex<T> {
+    /// Creates a new adaptive mutex in an unlocked, uncontended state.
+    pub fn new(value: T) -> AdaptiveMutex<T> {
+        AdaptiveMutex {
+            contended: AtomicBool::new(false),
+            inner: Mutex::new(value)
+        }
+    }
+}
+
+impl<T: ?Sized> AdaptiveMutex<T> {
+    /// Acquires the mutex, blocking the current thread until it is able to do so.
+    ///
+    /// While the mutex is not currently marked contended, this spins on `try_lock` for up to
+    /// `SPIN_ATTEMPTS` attempts. If none succeed, the mutex is marked contended and this call
+    /// (and calls from other threads) joins the MCS queue via `Mutex::lock` instead of spinning.
+    /// Every acquisition made through the queue clears the contended mark again once it completes.
+    pub fn lock<'a>(&'a self, slot: &'a mut Slot) -> Guard<'a, T> {
+        if !self.contended.load(Ordering::Relaxed) {
+            // `try_lock` ties its returned `Guard`'s lifetime to `slot`'s own, so retrying it
+            // across attempts needs reborrowing `slot` for that same `'a` on every attempt; see
+            // `reborrow_mut` for why that's sound despite the borrow checker not seeing it
+            // itself. Cast from a reborrow (rather than `slot` itself) since `slot` is still
+            // needed below, for the queueing fallback.
+            let spin_slot: *mut Slot = &mut *slot;
+            for _ in 0..SPIN_ATTEMPTS {
+                match self.inner.try_lock(unsafe { reborrow_mut(spin_slot) }) {
+                    Ok(guard) => return guard,
+                    Err(()) => pause()
+                }
+            }
+            self.contended.store(true, Ordering::Relaxed);
+        }
+
+        let guard = self.inner.lock(slot);
+        self.contended.store(false, Ordering::Relaxed);
+        guard
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AdaptiveMutex;
+    use crate::mutex::Slot;
+
+    use std::sync::Arc;
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    #[test]
+    fn test_low_contention_fast_path() {
+        let mut slot = Slot::new();
+        let m = AdaptiveMutex::new(0);
+        for _ in 0..100 {
+            *m.lock(&mut slot) += 1;
+        }
+        assert_eq!(*m.lock(&mut slot), 100);
+    }
+
+    #[test]
+    fn test_high_contention_falls_back_to_queue() {
+        let m = Arc::new(AdaptiveMutex::new(0u32));
+        const CONCURRENCY: u32 = 8;
+        const ITERS: u32 = 2000;
+
+        let (tx, rx) = channel();
+        for _ in 0..CONCURRENCY {
+            let m = m.clone();
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let mut slot = Slot::new();
+                for _ in 0..ITERS {
+                    *m.lock(&mut slot) += 1;
+                }
+                tx.send(()).unwrap();
+            });
+        }
+
+        drop(tx);
+        for _ in 0..CONCURRENCY {
+            rx.recv().unwrap();
+        }
+
+        let mut slot = Slot::new();
+        assert_eq!(*m.lock(&mut slot), CONCURRENCY * ITERS);
+    }
+
+    #[cfg(feature = "park-adaptive")]
+    #[test]
+    fn test_lock_adaptive_is_correct_uncontended() {
+        use super::lock_adaptive;
+        use crate::mutex::Mutex;
+
+        let mut slot = Slot::new();
+        let m = Mutex::new(0);
+        for _ in 0..100 {
+            *lock_adaptive(&m, &mut slot) += 1;
+        }
+        assert_eq!(*lock_adaptive(&m, &mut slot), 100);
+    }
+
+    #[cfg(feature = "park-adaptive")]
+    #[test]
+    fn test_lock_adaptive_reaches_park_stage_under_long_hold() {
+        use super::{lock_adaptive, park_stage_entries};
+        use crate::mutex::Mutex;
+
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let before = park_stage_entries();
+
+        let m = Arc::new(Mutex::new(0));
+        let mut holder_slot = Slot::new();
+        let guard = m.lock(&mut holder_slot);
+
+        let m2 = m.clone();
+        let waiter = thread::spawn(move || {
+            let mut slot = Slot::new();
+            *lock_adaptive(&m2, &mut slot) += 1;
+        });
+
+        // Give the waiter time to burn through the spin and yield bursts and reach the park stage.
+        thread::sleep(Duration::from_millis(50));
+        drop(guard);
+        waiter.join().unwrap();
+
+        assert!(park_stage_entries() > before);
+        assert_eq!(*m.lock(&mut Slot::new()), 1);
+    }
+}
@@ -0,0 +1,152 @@
+//! An owned guard for `Arc<Mutex<T>>`, and locking through `Weak<Mutex<T>>`.
+
+use core::mem;
+use core::ops::{Deref, DerefMut};
+
+use std::boxed::Box;
+use std::sync::{Arc, Weak};
+
+use crate::mutex::{Guard, Mutex, Slot};
+
+/// An owned RAII guard for a `Mutex<T>` held behind an `Arc`.
+///
+/// Unlike `Guard`, this guard owns a clone of the `Arc` and a heap-allocated `Slot` rather than
+/// borrowing them, so it has no lifetime tied to a stack frame: it can be stored in a struct,
+/// returned from a function, or moved into a closure that outlives the caller.
+pub struct ArcMutexGuard<T: ?Sized + 'static> {
+    // Must be declared before `arc` and `slot`: struct fields drop in declaration order, and
+    // this borrows from both of them, so it has to be released first.
+    guard: Guard<'static, T>,
+    arc: Arc<Mutex<T>>,
+    slot: Box<Slot>
+}
+
+impl<T: ?Sized + 'static> ArcMutexGuard<T> {
+    /// Locks `arc`, blocking the current thread until it is able to do so, and returns an owned
+    /// guard that keeps `arc` alive for as long as the guard exists.
+    pub fn lock(arc: Arc<Mutex<T>>) -> ArcMutexGuard<T> {
+        let mut slot = Box::new(Slot::new());
+        let guard = unsafe {
+            // SAFETY: `arc` and `slot` are moved into the `ArcMutexGuard` alongside `guard` and
+            // are declared to drop after it, so the borrows this guard holds stay valid for the
+            // whole time it exists.
+            let mutex: *const Mutex<T> = &*arc;
+            let slot: *mut Slot = &mut *slot;
+            mem::transmute::<Guard<T>, Guard<'static, T>>((*mutex).lock(&mut *slot))
+        };
+        ArcMutexGuard { guard: guard, arc: arc, slot: slot }
+    }
+}
+
+impl<T: ?Sized + 'static> Mutex<T> {
+    /// Locks this mutex, returning a guard that owns an `Arc` clone and a boxed `Slot` and so is
+    /// bounded entirely by `'static`, making it (for `Sized` `T`) storable in a type-erased
+    /// registry such as `Vec<Box<dyn Any>>`.
+    ///
+    /// Takes `&Arc<Self>` rather than `&self`, like `register_for_watchdog`, since the returned
+    /// guard needs its own strong reference to keep the data alive independent of the caller's.
+    pub fn lock_arc_static(self: &Arc<Self>) -> StaticArcGuard<T> {
+        StaticArcGuard { inner: ArcMutexGuard::lock(Arc::clone(self)) }
+    }
+}
+
+/// A `'static`, `Any`-compatible RAII guard for a `Mutex<T>` held behind an `Arc`.
+///
+/// This is `ArcMutexGuard` under another name, plus the guarantee (already implied by
+/// `ArcMutexGuard`'s own `T: 'static` bound) that the guard itself has no borrowed lifetime, so
+/// for `Sized` `T` it automatically implements `Any` and can be boxed into a type-erased registry
+/// (e.g. a plugin system's `Vec<Box<dyn Any>>`) that outlives the scope that acquired it.
+pub struct StaticArcGuard<T: ?Sized + 'static> {
+    inner: ArcMutexGuard<T>
+}
+
+impl<T: ?Sized + 'static> Deref for StaticArcGuard<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &*self.inner
+    }
+}
+
+impl<T: ?Sized + 'static> DerefMut for StaticArcGuard<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut *self.inner
+    }
+}
+
+/// Upgrades `weak` and locks the resulting mutex, returning an owned guard.
+///
+/// Returns `None` if the mutex has already been dropped, i.e. no strong references to it remain.
+pub fn lock_weak<T: ?Sized + 'static>(weak: &Weak<Mutex<T>>) -> Option<ArcMutexGuard<T>> {
+    weak.upgrade().map(ArcMutexGuard::lock)
+}
+
+// `ArcMutexGuard` owns its `Arc` clone and boxed `Slot` outright rather than borrowing them from a
+// stack frame, unlike `Guard`. The `same-thread-guard` feature's `*const ()` marker exists to pin
+// a *borrowed* `Guard` to the thread that acquired it; it has nothing to protect here, since
+// nothing about releasing the lock (see `Guard`'s `Drop` impl) depends on which thread does it.
+// Moving an `ArcMutexGuard` to another thread and dropping it there behaves identically to
+// `ArcMutexGuard::lock` having been called on that thread in the first place.
+unsafe impl<T: ?Sized + Send> Send for ArcMutexGuard<T> { }
+
+impl<T: ?Sized + 'static> Deref for ArcMutexGuard<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &*self.guard
+    }
+}
+
+impl<T: ?Sized + 'static> DerefMut for ArcMutexGuard<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut *self.guard
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{lock_weak, ArcMutexGuard};
+    use crate::mutex::Mutex;
+
+    use std::any::Any;
+    use std::boxed::Box;
+    use std::sync::Arc;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_lock_weak_live_target() {
+        let arc = Arc::new(Mutex::new(0));
+        let weak = Arc::downgrade(&arc);
+
+        {
+            let mut guard = lock_weak(&weak).unwrap();
+            *guard += 1;
+        }
+
+        assert_eq!(*ArcMutexGuard::lock(arc), 1);
+    }
+
+    #[test]
+    fn test_lock_weak_dropped_target() {
+        let arc = Arc::new(Mutex::new(0));
+        let weak = Arc::downgrade(&arc);
+        drop(arc);
+
+        assert!(lock_weak(&weak).is_none());
+    }
+
+    #[test]
+    fn test_lock_arc_static_survives_in_a_type_erased_registry() {
+        let arc = Arc::new(Mutex::new(0));
+
+        let mut registry: Vec<Box<dyn Any>> = Vec::new();
+        {
+            let mut guard = arc.lock_arc_static();
+            *guard += 1;
+            registry.push(Box::new(guard));
+        }
+
+        // Dropping the registry (and the guard along with it) must release the lock.
+        drop(registry);
+
+        assert_eq!(*ArcMutexGuard::lock(arc), 1);
+    }
+}
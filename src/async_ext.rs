@@ -0,0 +1,219 @@
+//! A scoped helper for holding the lock across an `.await` point, and a `Stream` of turns for
+//! pipeline stages that take turns on a shared resource.
+
+use core::future::Future;
+use core::marker::PhantomPinned;
+use core::ops::{Deref, DerefMut};
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::mutex::{Guard, Mutex, Slot};
+
+/// Acquires `mutex` without blocking the current thread, runs `f` with exclusive access to the
+/// protected data, and `.await`s the future it returns before releasing the lock.
+///
+/// Acquisition retries `Mutex::try_lock`, yielding back to the executor after every failed
+/// attempt (the task wakes itself, so it is polled again rather than parked). It never joins the
+/// MCS queue, so contending callers are not served in FIFO order. The lock is held across the
+/// awaited future because the `Guard` simply lives across the `.await` point inside this
+/// `async fn`'s generated state machine, same as any other borrow held across an await.
+pub async fn with_locked<'a, T, R, Fut, F>(mutex: &'a Mutex<T>, slot: &'a mut Slot, f: F) -> R
+    where T: ?Sized, F: FnOnce(&mut T) -> Fut, Fut: Future<Output = R>
+{
+    let mut guard = loop {
+        match mutex.try_lock(&mut *slot) {
+            Ok(guard) => break guard,
+            Err(()) => {
+                let mut yielded = false;
+                core::future::poll_fn(|cx| {
+                    if yielded {
+                        Poll::Ready(())
+                    } else {
+                        yielded = true;
+                        cx.waker().wake_by_ref();
+                        Poll::Pending
+                    }
+                }).await;
+            }
+        }
+    };
+    f(&mut *guard).await
+}
+
+/// A `Stream` of turns on a mutex, for pipeline stages that hand a shared resource back and
+/// forth: each item is a guard for one turn, and the next turn is only produced once the previous
+/// turn's guard has been dropped.
+///
+/// Returned by `lock_stream`. Like `with_locked`, acquisition uses the non-blocking
+/// `Mutex::try_lock` rather than joining the queue through `Mutex::lock`; a pending poll
+/// immediately wakes itself so the executor retries rather than parking.
+///
+/// This type is `!Unpin`: it hands out `Turn`s that reach back into its own `released` flag by
+/// address, so it must not move once it has produced a `Turn`. Pin it (e.g. with `Box::pin` or
+/// `futures_util::pin_mut!`) before polling.
+pub struct LockStream<'a, T: ?Sized> {
+    mutex: &'a Mutex<T>,
+    slot: &'a mut Slot,
+    // Whether the most recently issued `Turn` has already been dropped. Starts `true` since there
+    // is no outstanding turn yet. `poll_next` must not touch `slot` while this is `false`: it is
+    // exclusively borrowed by the live `Turn`'s `Guard`, and reborrowing it here would alias that
+    // borrow.
+    released: AtomicBool,
+    _pinned: PhantomPinned
+}
+
+/// One turn's worth of exclusive access, yielded by `LockStream`.
+///
+/// Dropping a `Turn` both releases the underlying `Mutex` (via the inner `Guard`) and signals the
+/// owning `LockStream` that it may produce the next turn.
+pub struct Turn<'a, T: ?Sized> {
+    guard: Guard<'a, T>,
+    released: *const AtomicBool
+}
+
+impl<'a, T: ?Sized> Deref for Turn<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &*self.guard
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for Turn<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut *self.guard
+    }
+}
+
+impl<'a, T: ?Sized> Drop for Turn<'a, T> {
+    fn drop(&mut self) {
+        // Safety: `released` points at the `AtomicBool` field of the `LockStream` that produced
+        // this `Turn`, which cannot have moved or been dropped: `LockStream` is `!Unpin`, and it
+        // only hands out one `Turn` at a time, keeping this `Turn` (and thus this pointer) alive
+        // no longer than the `LockStream` itself.
+        unsafe { (*self.released).store(true, Ordering::Release) };
+    }
+}
+
+/// Returns a `Stream` of turns on `mutex`, one `Turn` per acquisition.
+///
+/// See `LockStream` for the pinning requirement this imposes.
+pub fn lock_stream<'a, T: ?Sized>(mutex: &'a Mutex<T>, slot: &'a mut Slot) -> LockStream<'a, T> {
+    LockStream { mutex, slot, released: AtomicBool::new(true), _pinned: PhantomPinned }
+}
+
+impl<'a, T: ?Sized> Stream for LockStream<'a, T> {
+    type Item = Turn<'a, T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // Safety: none of the projected fields are moved out of; `slot` is only ever reborrowed.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if !this.released.load(Ordering::Acquire) {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        // Safety: `released` just confirmed the previous `Turn`'s `Guard` (if any) has already
+        // been dropped, so its exclusive borrow of `*this.slot` has ended; reborrowing it here for
+        // the lifetime `'a` (rather than the shorter lifetime of this `&mut Self`) is sound
+        // because `this.mutex` and `this.slot` are themselves only ever accessed for `'a` as a
+        // whole, matching the actual lifetime `LockStream<'a, T>` was constructed with.
+        let slot: &'a mut Slot = unsafe { &mut *(this.slot as *mut Slot) };
+
+        match this.mutex.try_lock(slot) {
+            Ok(guard) => {
+                this.released.store(false, Ordering::Release);
+                Poll::Ready(Some(Turn { guard, released: &this.released as *const AtomicBool }))
+            }
+            Err(()) => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::with_locked;
+    use crate::mutex::{Mutex, Slot};
+
+    use std::sync::Arc;
+    use std::time::Duration;
+    use std::vec::Vec;
+
+    #[tokio::test]
+    async fn test_with_locked_holds_lock_across_await() {
+        let mutex = Arc::new(Mutex::new(0u32));
+
+        let mut handles = Vec::new();
+        for _ in 0..4 {
+            let mutex = mutex.clone();
+            handles.push(tokio::spawn(async move {
+                let mut slot = Slot::new();
+                with_locked(&mutex, &mut slot, |value| async move {
+                    let before = *value;
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                    // If another task's critical section interleaved with ours despite the
+                    // lock being held, `value` would have moved since we read it above.
+                    assert_eq!(*value, before);
+                    *value += 1;
+                }).await;
+            }));
+        }
+
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        let mut slot = Slot::new();
+        assert_eq!(*mutex.lock(&mut slot), 4);
+    }
+
+    #[tokio::test]
+    async fn test_lock_stream_serializes_turns() {
+        use super::lock_stream;
+        use futures::StreamExt;
+
+        let mutex = Mutex::new(0u32);
+        let mut slot = Slot::new();
+        let stream = lock_stream(&mutex, &mut slot);
+        tokio::pin!(stream);
+
+        for expected in 1..=3u32 {
+            let mut turn = stream.next().await.expect("stream should never end");
+            *turn += 1;
+            assert_eq!(*turn, expected);
+            drop(turn);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lock_stream_does_not_yield_next_turn_before_previous_is_dropped() {
+        use super::lock_stream;
+        use futures::StreamExt;
+
+        let mutex = Mutex::new(0u32);
+        let mut slot = Slot::new();
+        let stream = lock_stream(&mutex, &mut slot);
+        tokio::pin!(stream);
+
+        let turn = stream.next().await.expect("stream should never end");
+
+        // While `turn` is still held, polling again must not produce a second turn: the mutex
+        // is still locked, so this should time out rather than resolve.
+        let second = tokio::time::timeout(Duration::from_millis(50), stream.next()).await;
+        assert!(second.is_err(), "a second turn was produced before the first was dropped");
+
+        drop(turn);
+
+        let turn = tokio::time::timeout(Duration::from_millis(50), stream.next())
+            .await
+            .expect("stream should resolve once the previous turn is dropped")
+            .expect("stream should never end");
+        drop(turn);
+    }
+}
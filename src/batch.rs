@@ -0,0 +1,80 @@
+//! Best-effort, non-blocking acquisition across a fixed-size set of mutexes.
+//!
+//! Unlike `lock_both` (see the `combined` module), which acquires two mutexes *together* using a
+//! deterministic address ordering to avoid deadlock, `try_lock_available` never blocks and so has
+//! no ordering concerns at all: each mutex is tried independently, and whichever are free get
+//! acquired while the rest are simply skipped.
+
+use crate::mutex::{Guard, Mutex, Slot};
+
+/// Attempts to acquire every mutex in `mutexes`, skipping any that are currently held.
+///
+/// Returns one slot per input, `Some(guard)` for the ones this call acquired and `None` for the
+/// ones that were contended. This never blocks: it's equivalent to calling `try_lock` on each
+/// mutex in turn, so it suits opportunistic batch processing where working on whichever subset is
+/// currently free is fine, as opposed to needing all of them at once.
+pub fn try_lock_available<'a, T, const N: usize>(
+    mutexes: [&'a Mutex<T>; N],
+    slots: &'a mut [Slot; N]
+) -> [Option<Guard<'a, T>>; N] {
+    let mut guards: [Option<Guard<'a, T>>; N] = core::array::from_fn(|_| None);
+    for (i, slot) in slots.iter_mut().enumerate() {
+        guards[i] = mutexes[i].try_lock(slot).ok();
+    }
+    guards
+}
+
+#[cfg(test)]
+mod test {
+    use super::try_lock_available;
+    use crate::mutex::{Mutex, Slot};
+
+    use std::thread;
+
+    #[test]
+    fn test_try_lock_available_skips_held_mutexes() {
+        let a = Mutex::new(1);
+        let b = Mutex::new(2);
+        let c = Mutex::new(3);
+
+        let mut b_slot = Slot::new();
+        let held = b.lock(&mut b_slot);
+
+        let mut slots = [Slot::new(), Slot::new(), Slot::new()];
+        let guards = try_lock_available([&a, &b, &c], &mut slots);
+
+        assert!(guards[0].is_some());
+        assert!(guards[1].is_none());
+        assert!(guards[2].is_some());
+
+        drop(held);
+    }
+
+    #[test]
+    fn test_try_lock_available_reflects_contention_from_another_thread() {
+        let a = Mutex::new(0);
+        let a = std::sync::Arc::new(a);
+
+        let a2 = a.clone();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel();
+        let t = thread::spawn(move || {
+            let mut slot = Slot::new();
+            let _guard = a2.lock(&mut slot);
+            tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+        });
+
+        rx.recv().unwrap();
+
+        let b = Mutex::new(0);
+        let mut slots = [Slot::new(), Slot::new()];
+        let guards = try_lock_available([&*a, &b], &mut slots);
+
+        assert!(guards[0].is_none());
+        assert!(guards[1].is_some());
+
+        release_tx.send(()).unwrap();
+        t.join().unwrap();
+    }
+}
@@ -0,0 +1,146 @@
+//! Acquiring two mutexes together without risking deadlock, via `lock_both`.
+//!
+//! Locking two unrelated mutexes by calling `lock` on each in turn is deadlock-prone: if one
+//! thread locks `a` then `b` while another locks `b` then `a`, the two can wait on each other
+//! forever. `lock_both` sidesteps this by always acquiring the two mutexes in the same order
+//! regardless of the order they're passed in, determined by comparing their addresses, so any two
+//! calls racing over the same pair of mutexes agree on an order. The resulting `CombinedGuard`
+//! releases them in the reverse of whatever order they were actually acquired in, same as nested
+//! `Guard`s would if acquired in that order and dropped normally.
+
+use core::mem::ManuallyDrop;
+
+use crate::mutex::{Guard, Mutex, Slot};
+
+/// An RAII guard holding two mutexes locked together, acquired via `lock_both`.
+#[must_use]
+pub struct CombinedGuard<'a, A: ?Sized + 'a, B: ?Sized + 'a> {
+    a: ManuallyDrop<Guard<'a, A>>,
+    b: ManuallyDrop<Guard<'a, B>>,
+    // Whether `a` was the mutex actually acquired first, so `Drop` can release in the reverse of
+    // the real acquisition order rather than always releasing `b` before `a`.
+    a_first: bool
+}
+
+impl<'a, A: ?Sized, B: ?Sized> CombinedGuard<'a, A, B> {
+    /// Returns a reference to the data protected by the first mutex.
+    pub fn a(&self) -> &A {
+        &self.a
+    }
+
+    /// Returns a reference to the data protected by the second mutex.
+    pub fn b(&self) -> &B {
+        &self.b
+    }
+
+    /// Returns a mutable reference to the data protected by the first mutex.
+    pub fn a_mut(&mut self) -> &mut A {
+        &mut self.a
+    }
+
+    /// Returns a mutable reference to the data protected by the second mutex.
+    pub fn b_mut(&mut self) -> &mut B {
+        &mut self.b
+    }
+}
+
+impl<'a, A: ?Sized, B: ?Sized> Drop for CombinedGuard<'a, A, B> {
+    fn drop(&mut self) {
+        unsafe {
+            if self.a_first {
+                ManuallyDrop::drop(&mut self.b);
+                ManuallyDrop::drop(&mut self.a);
+            } else {
+                ManuallyDrop::drop(&mut self.a);
+                ManuallyDrop::drop(&mut self.b);
+            }
+        }
+    }
+}
+
+/// Locks both `a` and `b`, returning a single guard that releases both when dropped.
+///
+/// The two mutexes are always acquired in the same relative order (by address) no matter which
+/// order they're passed to `lock_both` in, so that concurrent calls locking the same pair of
+/// mutexes (in either argument order) can never deadlock against each other.
+pub fn lock_both<'a, A: ?Sized, B: ?Sized>(
+    a: &'a Mutex<A>, slot_a: &'a mut Slot,
+    b: &'a Mutex<B>, slot_b: &'a mut Slot
+) -> CombinedGuard<'a, A, B> {
+    let addr_a = a as *const Mutex<A> as *const () as usize;
+    let addr_b = b as *const Mutex<B> as *const () as usize;
+
+    if addr_a <= addr_b {
+        let guard_a = a.lock(slot_a);
+        let guard_b = b.lock(slot_b);
+        CombinedGuard { a: ManuallyDrop::new(guard_a), b: ManuallyDrop::new(guard_b), a_first: true }
+    } else {
+        let guard_b = b.lock(slot_b);
+        let guard_a = a.lock(slot_a);
+        CombinedGuard { a: ManuallyDrop::new(guard_a), b: ManuallyDrop::new(guard_b), a_first: false }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::lock_both;
+    use crate::mutex::{Mutex, Slot};
+
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_lock_both_locks_and_unlocks_both() {
+        let a = Mutex::new(1);
+        let b = Mutex::new(2);
+        let mut slot_a = Slot::new();
+        let mut slot_b = Slot::new();
+
+        {
+            let mut guard = lock_both(&a, &mut slot_a, &b, &mut slot_b);
+            *guard.a_mut() += 10;
+            *guard.b_mut() += 20;
+        }
+
+        assert_eq!(*a.lock(&mut slot_a), 11);
+        assert_eq!(*b.lock(&mut slot_b), 22);
+    }
+
+    #[test]
+    fn test_lock_both_reverse_argument_order_does_not_deadlock() {
+        // Two threads locking the same pair of mutexes in opposite argument order should still
+        // both make progress, since `lock_both` normalizes the acquisition order internally.
+        let a = Arc::new(Mutex::new(0u32));
+        let b = Arc::new(Mutex::new(0u32));
+
+        let (a1, b1) = (a.clone(), b.clone());
+        let t1 = thread::spawn(move || {
+            for _ in 0..1000 {
+                let mut slot_a = Slot::new();
+                let mut slot_b = Slot::new();
+                let mut guard = lock_both(&a1, &mut slot_a, &b1, &mut slot_b);
+                *guard.a_mut() += 1;
+                *guard.b_mut() += 1;
+            }
+        });
+
+        let (a2, b2) = (a.clone(), b.clone());
+        let t2 = thread::spawn(move || {
+            for _ in 0..1000 {
+                let mut slot_b = Slot::new();
+                let mut slot_a = Slot::new();
+                let mut guard = lock_both(&b2, &mut slot_b, &a2, &mut slot_a);
+                *guard.a_mut() += 1;
+                *guard.b_mut() += 1;
+            }
+        });
+
+        t1.join().unwrap();
+        t2.join().unwrap();
+
+        let mut slot_a = Slot::new();
+        let mut slot_b = Slot::new();
+        assert_eq!(*a.lock(&mut slot_a), 2000);
+        assert_eq!(*b.lock(&mut slot_b), 2000);
+    }
+}
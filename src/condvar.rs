@@ -0,0 +1,124 @@
+//! Interop for pairing this crate's `Mutex` with `std::sync::Condvar`.
+//!
+//! `std::sync::Condvar::wait` only accepts a `std::sync::MutexGuard`, so it can't wait directly
+//! against a critical section protected by this crate's own `Mutex`. `CondvarBridge` provides a
+//! separate, data-free `std::sync::Mutex<()>` purely to drive the condvar handshake, while the
+//! actual protected data continues to live behind the MCS `Mutex` as usual.
+//!
+//! Checking the wait condition and calling `Condvar::wait` both happen while the handshake lock
+//! is held, and `notify_one`/`notify_all` both take that same lock before signaling, so a
+//! notification can never land in the gap between a waiter deciding to wait and actually blocking
+//! on the condvar; that gap is exactly where lost wakeups normally sneak in.
+
+use std::sync::{Condvar, Mutex as StdMutex};
+
+use crate::mutex::{Guard, Mutex, Slot};
+use crate::reborrow::reborrow_mut;
+
+/// Pairs this crate's `Mutex` with a `std::sync::Condvar` for condition-variable-style waiting.
+///
+/// See the module documentation for how lost wakeups are avoided. The handshake lock this holds
+/// has no bearing on the protected data itself; it exists solely to make `wait_while` and
+/// `notify_one`/`notify_all` mutually exclusive with each other.
+pub struct CondvarBridge {
+    handshake: StdMutex<()>,
+    condvar: Condvar
+}
+
+impl CondvarBridge {
+    /// Creates a new bridge with no threads waiting.
+    pub fn new() -> CondvarBridge {
+        CondvarBridge { handshake: StdMutex::new(()), condvar: Condvar::new() }
+    }
+
+    /// Blocks the current thread until `condition` holds, returning a `Guard` over `mutex` with
+    /// `condition` known to be true at the moment it's returned.
+    ///
+    /// Holds neither `mutex` nor the handshake lock while actually parked on the condvar (the
+    /// condvar wait releases the handshake lock), so a concurrent `notify_one`/`notify_all` is
+    /// free to run; re-acquires `mutex` to re-check
+    /// `condition` on every wakeup, matching the usual condvar idiom of looping rather than
+    /// trusting a single wakeup to mean the condition is still true.
+    pub fn wait_while<'a, T: ?Sized, F>(&self, mutex: &'a Mutex<T>, slot: &'a mut Slot, mut condition: F) -> Guard<'a, T>
+        where F: FnMut(&T) -> bool
+    {
+        // `mutex.lock(slot)` ties the returned `Guard`'s lifetime to `slot`'s own, so calling it
+        // again on the next loop iteration needs reborrowing `slot` for that same `'a`; see
+        // `reborrow_mut` for why that's sound despite the borrow checker not seeing it itself.
+        let slot: *mut Slot = slot;
+        let mut handshake = self.handshake.lock().unwrap();
+        loop {
+            {
+                // Safety: the previous iteration's reborrow has already ended, either by
+                // returning (which exits the function before this point is reached again) or by
+                // the guard above going out of scope at the end of this block.
+                let guard = mutex.lock(unsafe { reborrow_mut(slot) });
+                if condition(&*guard) {
+                    return guard;
+                }
+            }
+            handshake = self.condvar.wait(handshake).unwrap();
+        }
+    }
+
+    /// Wakes up one thread blocked in `wait_while`.
+    ///
+    /// Callers should apply whatever change might satisfy a waiter's `condition` through the
+    /// protected `Mutex` (not through this bridge) before calling this.
+    pub fn notify_one(&self) {
+        let _handshake = self.handshake.lock().unwrap();
+        self.condvar.notify_one();
+    }
+
+    /// Wakes up every thread blocked in `wait_while`.
+    pub fn notify_all(&self) {
+        let _handshake = self.handshake.lock().unwrap();
+        self.condvar.notify_all();
+    }
+}
+
+impl Default for CondvarBridge {
+    fn default() -> CondvarBridge {
+        CondvarBridge::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CondvarBridge;
+    use crate::mutex::{Mutex, Slot};
+
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+    use std::thread;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_producer_consumer_round_trip() {
+        const ITEMS: u32 = 100;
+
+        let queue = Arc::new(Mutex::new(VecDeque::new()));
+        let bridge = Arc::new(CondvarBridge::new());
+
+        let queue2 = queue.clone();
+        let bridge2 = bridge.clone();
+        let consumer = thread::spawn(move || {
+            let mut slot = Slot::new();
+            let mut received = Vec::new();
+            while received.len() < ITEMS as usize {
+                let mut guard = bridge2.wait_while(&queue2, &mut slot, |q: &VecDeque<u32>| !q.is_empty());
+                received.push(guard.pop_front().unwrap());
+            }
+            received
+        });
+
+        let mut slot = Slot::new();
+        for item in 0..ITEMS {
+            queue.lock(&mut slot).push_back(item);
+            bridge.notify_one();
+        }
+
+        let received = consumer.join().unwrap();
+        assert_eq!(received, (0..ITEMS).collect::<Vec<_>>());
+    }
+}
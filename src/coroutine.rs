@@ -0,0 +1,80 @@
+//! An owned guard for locking a `'static` mutex from a stackless coroutine.
+//!
+//! A stackless coroutine (an `async fn`'s generated state machine, or a hand-written generator)
+//! stores everything held across a suspension point inline in its state struct rather than on a
+//! call stack. A `Guard`, which borrows a `Slot` living in the *caller's* stack frame, can't be
+//! held across a suspension that way: the coroutine's state struct would need a self-referential
+//! borrow into itself. `lock_detached` sidesteps this the same way `arc_guard` does, by putting
+//! the `Slot` on the heap, so the guard it returns owns everything it borrows from and has no
+//! lifetime tied to any particular stack frame.
+
+use core::mem;
+use core::ops::{Deref, DerefMut};
+
+use std::boxed::Box;
+
+use crate::mutex::{Guard, Mutex, Slot};
+
+/// An owned RAII guard for a `'static` `Mutex<T>`, suitable for storing inline in a stackless
+/// coroutine's state across a suspension point.
+pub struct CoroutineGuard<T: ?Sized + 'static> {
+    // Must be declared before `slot`: struct fields drop in declaration order, and this borrows
+    // from it, so it has to be released first.
+    guard: Guard<'static, T>,
+    slot: Box<Slot>
+}
+
+/// Locks `mutex`, blocking the current thread until it is able to do so, and returns an owned
+/// guard backed by a heap-allocated `Slot` instead of one borrowed from the caller's stack frame.
+///
+/// `mutex` must be `'static` since the returned guard carries no lifetime of its own; a `static
+/// Mutex<T>` or one behind a leaked or otherwise `'static` reference both work.
+pub fn lock_detached<T: ?Sized + 'static>(mutex: &'static Mutex<T>) -> CoroutineGuard<T> {
+    let mut slot = Box::new(Slot::new());
+    let guard = unsafe {
+        // SAFETY: `slot` is moved into the `CoroutineGuard` alongside `guard` and is declared to
+        // drop after it, so the borrow this guard holds into it stays valid for as long as the
+        // guard exists.
+        let slot: *mut Slot = &mut *slot;
+        mem::transmute::<Guard<T>, Guard<'static, T>>(mutex.lock(&mut *slot))
+    };
+    CoroutineGuard { guard: guard, slot: slot }
+}
+
+impl<T: ?Sized + 'static> Deref for CoroutineGuard<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &*self.guard
+    }
+}
+
+impl<T: ?Sized + 'static> DerefMut for CoroutineGuard<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut *self.guard
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::lock_detached;
+    use crate::mutex::Mutex;
+
+    lazy_static! {
+        static ref COUNTER: Mutex<u32> = Mutex::new(0);
+    }
+
+    #[test]
+    fn test_lock_detached_is_static_and_releases_on_drop() {
+        {
+            let mut guard = lock_detached(&COUNTER);
+            *guard += 1;
+        }
+
+        let mut guard = lock_detached(&COUNTER);
+        assert_eq!(*guard, 1);
+        *guard += 1;
+        drop(guard);
+
+        assert_eq!(*lock_detached(&COUNTER), 2);
+    }
+}
@@ -0,0 +1,159 @@
+//! A `Mutex` built on a global critical section via the `critical-section` crate instead of the
+//! lock-free MCS queue this crate otherwise uses.
+//!
+//! This trades away the MCS queue's fairness and scalability for portability and interrupt
+//! safety: every `lock` and `try_lock` call disables interrupts (or whatever the platform's
+//! `critical-section` implementation does) for the duration of the check, so contended callers
+//! spin outside the critical section rather than queueing. This module serves two purposes:
+//!
+//! - On targets without pointer-width atomics (some AVR/MSP430 chips), the MCS queue can't be
+//!   built at all, so `lib.rs` uses this as the crate's only `Mutex`/`Slot`/`Guard`.
+//! - On any target, it's also exported as `CsMutex`/`CsSlot`/`CsGuard`, an explicit opt-in for
+//!   single-core embedded code that services interrupts: spinning on a lock held by a
+//!   lower-priority task that an interrupt handler just preempted can deadlock the core, since the
+//!   handler will spin forever waiting for a holder that can never run again. Disabling interrupts
+//!   around the critical section instead of queueing avoids that specific hazard.
+
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+
+extern crate critical_section;
+
+use crate::reborrow::reborrow_mut;
+
+/// A waiter handle, kept only for API parity with the MCS-backed `Slot`.
+///
+/// The critical-section fallback doesn't queue waiters, so this carries no state.
+pub struct Slot;
+
+impl Slot {
+    pub fn new() -> Slot {
+        Slot
+    }
+}
+
+/// A mutual exclusion primitive built on a global critical section.
+///
+/// See the module documentation for how this differs from the MCS-backed `Mutex` used on targets
+/// with pointer-width atomics.
+pub struct Mutex<T: ?Sized> {
+    locked: UnsafeCell<bool>,
+    data: UnsafeCell<T>
+}
+
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> { }
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> { }
+
+impl<T> Mutex<T> {
+    /// Creates a new mutex in an unlocked state ready for use.
+    pub fn new(value: T) -> Mutex<T> {
+        Mutex {
+            locked: UnsafeCell::new(false),
+            data: UnsafeCell::new(value)
+        }
+    }
+
+    /// Consumes this mutex, returning the underlying data.
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Attempts to acquire this lock. Does not block.
+    pub fn try_lock<'a>(&'a self, _slot: &'a mut Slot) -> Result<Guard<'a, T>, ()> {
+        critical_section::with(|_| unsafe {
+            if *self.locked.get() {
+                Err(())
+            } else {
+                *self.locked.get() = true;
+                Ok(Guard { lock: self })
+            }
+        })
+    }
+
+    /// Acquires a mutex, blocking (spinning) the current thread until it is able to do so.
+    pub fn lock<'a>(&'a self, slot: &'a mut Slot) -> Guard<'a, T> {
+        // `try_lock` ties its returned `Guard`'s lifetime to `slot`'s own, so retrying it in a
+        // loop needs reborrowing `slot` for that same `'a` on every attempt; see `reborrow_mut`
+        // for why that's sound despite the borrow checker not seeing it itself.
+        let slot: *mut Slot = slot;
+        loop {
+            if let Ok(guard) = self.try_lock(unsafe { reborrow_mut(slot) }) {
+                return guard;
+            }
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.data.get() }
+    }
+}
+
+/// An RAII implementation of a "scoped lock" of a `Mutex`. See `Mutex` for details.
+#[must_use]
+pub struct Guard<'a, T: ?Sized + 'a> {
+    lock: &'a Mutex<T>
+}
+
+impl<'a, T: ?Sized> Deref for Guard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for Guard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for Guard<'a, T> {
+    fn drop(&mut self) {
+        critical_section::with(|_| unsafe {
+            *self.lock.locked.get() = false;
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Mutex, Slot};
+
+    #[test]
+    fn smoke() {
+        let mut slot = Slot::new();
+        let m = Mutex::new(0);
+        *m.lock(&mut slot) += 1;
+        assert_eq!(*m.lock(&mut slot), 1);
+    }
+
+    #[test]
+    fn try_lock_fails_while_held() {
+        let mut slot_a = Slot::new();
+        let mut slot_b = Slot::new();
+        let m = Mutex::new(());
+        let guard = m.lock(&mut slot_a);
+        assert!(m.try_lock(&mut slot_b).is_err());
+        drop(guard);
+        assert!(m.try_lock(&mut slot_b).is_ok());
+    }
+
+    // A single-core interrupt handler preempts "main" code on the same thread of execution, so a
+    // same-thread reentrant attempt (rather than a second OS thread) is actually the faithful way
+    // to model "main holds the lock, then an interrupt fires and tries to touch it too".
+    #[test]
+    fn test_main_and_interrupt_accessors_are_mutually_exclusive() {
+        let mut main_slot = Slot::new();
+        let mut interrupt_slot = Slot::new();
+        let m = Mutex::new(0);
+
+        let main_guard = m.lock(&mut main_slot);
+        assert!(m.try_lock(&mut interrupt_slot).is_err(), "interrupt handler must not re-enter while main holds the lock");
+
+        drop(main_guard);
+        assert!(m.try_lock(&mut interrupt_slot).is_ok(), "lock must be available once main releases it");
+    }
+}
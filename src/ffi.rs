@@ -0,0 +1,85 @@
+//! C ABI-compatible layout and lock/unlock shims for handing a `Mutex<T>` across an FFI
+//! boundary to code that only needs to acquire and release it opaquely.
+//!
+//! These functions are generic and therefore not directly callable from C: a per-type,
+//! `#[no_mangle]` wrapper monomorphizing `T` is still needed on the Rust side. What this module
+//! provides is the `#[repr(C)]` layout guarantee on `Mutex` (see its definition) and the
+//! lock/unlock pair implemented in terms of raw pointers instead of borrows, which is the part
+//! that's awkward to get right by hand.
+
+use core::mem;
+
+use crate::mutex::{Guard, Mutex, Slot};
+
+const _: () = {
+    assert!(mem::size_of::<Mutex<u32>>() >= mem::size_of::<u32>());
+    assert!(mem::align_of::<Mutex<u32>>() >= mem::align_of::<u32>());
+};
+
+/// Locks `mutex`, blocking until it is available, using `slot` as the waiter's queue node, and
+/// leaves it locked when this call returns.
+///
+/// The lock must later be released with `mcs_mutex_unlock` using the same `mutex`/`slot` pair.
+///
+/// # Safety
+///
+/// `mutex` and `slot` must be valid, properly aligned, non-null pointers, and `slot` must remain
+/// valid and not be reused for another acquisition until the matching `mcs_mutex_unlock` call.
+#[cfg(feature = "repr-c")]
+pub unsafe extern "C" fn mcs_mutex_lock<T>(mutex: *const Mutex<T>, slot: *mut Slot) {
+    let guard = (*mutex).lock(&mut *slot);
+    mem::forget(guard);
+}
+
+/// Releases a lock previously acquired via `mcs_mutex_lock` on the same `mutex`/`slot` pair.
+///
+/// # Safety
+///
+/// Must be paired with a prior `mcs_mutex_lock(mutex, slot)` call, and must not be called more
+/// than once per acquisition.
+#[cfg(feature = "repr-c")]
+pub unsafe extern "C" fn mcs_mutex_unlock<T>(mutex: *const Mutex<T>, slot: *mut Slot) {
+    drop(Guard::from_raw_parts(&*mutex, &*slot));
+}
+
+#[cfg(test)]
+mod test {
+    use super::{mcs_mutex_lock, mcs_mutex_unlock};
+    use crate::mutex::{Mutex, Slot};
+
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_c_abi_shims_round_trip_and_exclude() {
+        let mutex = Arc::new(Mutex::new(0usize));
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        let threads: Vec<_> = (0..4).map(|_| {
+            let mutex = mutex.clone();
+            let counter = counter.clone();
+            thread::spawn(move || {
+                let mut slot = Slot::new();
+                for _ in 0..1000 {
+                    unsafe {
+                        mcs_mutex_lock(&*mutex as *const Mutex<usize>, &mut slot as *mut Slot);
+                        let ptr = mutex.data_ptr();
+                        *ptr += 1;
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        mcs_mutex_unlock(&*mutex as *const Mutex<usize>, &mut slot as *mut Slot);
+                    }
+                }
+            })
+        }).collect();
+
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        assert_eq!(counter.load(Ordering::SeqCst), 4000);
+        let mut slot = Slot::new();
+        assert_eq!(*mutex.lock(&mut slot), 4000);
+    }
+}
@@ -1,14 +1,121 @@
 #![cfg_attr(feature = "unstable", feature(asm, const_fn, generic_param_attrs, dropck_eyepatch))]
+#![cfg_attr(feature = "allocator-api", feature(allocator_api))]
 
 #![no_std]
 
 #[cfg(test)]
 #[macro_use]
 extern crate lazy_static;
-#[cfg(test)]
+#[cfg(any(test, feature = "std"))]
 extern crate std;
+#[cfg(any(all(test, feature = "async"), feature = "tokio-blocking"))]
+extern crate tokio;
+#[cfg(feature = "spin-compat")]
+extern crate spin;
+#[cfg(feature = "async")]
+extern crate futures_core;
+
+// The MCS queue is built on `AtomicPtr`, which some targets (certain AVR/MSP430 chips) don't
+// support. On those targets, fall back to a `critical-section`-based mutex instead of the MCS
+// queue; without that feature enabled there's no safe implementation available at all.
+#[cfg(all(not(target_has_atomic = "ptr"), not(feature = "critical-section")))]
+compile_error!(
+    "mcs requires pointer-width atomics, which this target doesn't have; enable the \
+     `critical-section` feature to use the critical-section-based fallback implementation instead"
+);
 
+#[cfg(all(target_has_atomic = "ptr", any(feature = "adaptive", feature = "park-adaptive")))]
+mod adaptive;
+#[cfg(all(target_has_atomic = "ptr", feature = "async"))]
+mod async_ext;
+#[cfg(all(target_has_atomic = "ptr", feature = "std"))]
+mod arc_guard;
+#[cfg(all(target_has_atomic = "ptr", feature = "batch"))]
+mod batch;
+#[cfg(all(target_has_atomic = "ptr", feature = "combined"))]
+mod combined;
+#[cfg(all(target_has_atomic = "ptr", feature = "std"))]
+mod condvar;
+#[cfg(all(target_has_atomic = "ptr", feature = "coroutine"))]
+mod coroutine;
+#[cfg(feature = "critical-section")]
+mod cs_mutex;
+#[cfg(all(target_has_atomic = "ptr", feature = "repr-c"))]
+mod ffi;
+#[cfg(all(target_has_atomic = "ptr", feature = "reclaim"))]
+mod reclaim;
+#[cfg(all(target_has_atomic = "ptr", feature = "lockdep"))]
+mod lockdep;
+#[cfg(all(target_has_atomic = "ptr", feature = "rayon"))]
+mod rayon_ext;
+#[macro_use]
+mod macros;
+#[cfg(all(target_has_atomic = "ptr", feature = "maybe-guard"))]
+mod maybe;
+#[cfg(target_has_atomic = "ptr")]
 mod mutex;
 mod pause;
+mod reborrow;
+#[cfg(feature = "spin-compat")]
+mod spin_compat;
+#[cfg(all(target_has_atomic = "ptr", feature = "split"))]
+mod split;
+#[cfg(all(target_has_atomic = "ptr", feature = "tokio-blocking"))]
+mod tokio_ext;
+#[cfg(all(target_has_atomic = "ptr", feature = "watchdog"))]
+mod watchdog;
 
-pub use mutex::{Slot, Mutex, Guard};
+#[cfg(target_has_atomic = "ptr")]
+pub use crate::mutex::{Slot, Mutex, Guard};
+#[cfg(all(not(target_has_atomic = "ptr"), feature = "critical-section"))]
+pub use cs_mutex::{Slot, Mutex, Guard};
+// On targets that do have pointer-width atomics (and so use the MCS-based `Mutex` above as the
+// primary implementation), the critical-section-based `Mutex` is still available under these
+// names as an explicit opt-in for single-core embedded code serviced by interrupt handlers, where
+// MCS spinning risks a deadlock against a preempted, lower-priority holder.
+#[cfg(all(target_has_atomic = "ptr", feature = "critical-section"))]
+pub use cs_mutex::{Slot as CsSlot, Mutex as CsMutex, Guard as CsGuard};
+#[cfg(all(target_has_atomic = "ptr", feature = "adaptive"))]
+pub use adaptive::AdaptiveMutex;
+#[cfg(all(target_has_atomic = "ptr", feature = "park-adaptive"))]
+pub use adaptive::lock_adaptive;
+#[cfg(all(target_has_atomic = "ptr", feature = "async"))]
+pub use async_ext::with_locked;
+#[cfg(all(target_has_atomic = "ptr", feature = "async"))]
+pub use async_ext::{LockStream, Turn, lock_stream};
+#[cfg(all(target_has_atomic = "ptr", feature = "batch"))]
+pub use batch::try_lock_available;
+#[cfg(all(target_has_atomic = "ptr", feature = "combined"))]
+pub use combined::{CombinedGuard, lock_both};
+#[cfg(all(target_has_atomic = "ptr", feature = "std"))]
+pub use condvar::CondvarBridge;
+#[cfg(all(target_has_atomic = "ptr", feature = "coroutine"))]
+pub use coroutine::{CoroutineGuard, lock_detached};
+#[cfg(all(target_has_atomic = "ptr", feature = "maybe-guard"))]
+pub use maybe::{MaybeGuard, lock_maybe};
+#[cfg(all(target_has_atomic = "ptr", feature = "raw-token"))]
+pub use crate::mutex::RawLockToken;
+#[cfg(all(target_has_atomic = "ptr", feature = "release-hook"))]
+pub use crate::mutex::HookedGuard;
+#[cfg(all(target_has_atomic = "ptr", feature = "std"))]
+pub use crate::mutex::MeasuredGuard;
+#[cfg(all(target_has_atomic = "ptr", feature = "lazy-init"))]
+pub use crate::mutex::MaybeUninitGuard;
+#[cfg(all(target_has_atomic = "ptr", feature = "permit"))]
+pub use crate::mutex::Permit;
+#[cfg(all(target_has_atomic = "ptr", feature = "std"))]
+pub use crate::arc_guard::{ArcMutexGuard, StaticArcGuard, lock_weak};
+#[cfg(all(target_has_atomic = "ptr", feature = "repr-c"))]
+pub use ffi::{mcs_mutex_lock, mcs_mutex_unlock};
+#[cfg(all(target_has_atomic = "ptr", feature = "reclaim"))]
+pub use reclaim::{ReclaimingGuard, ReclaimingMutex, ReclamationHooks};
+#[cfg(all(target_has_atomic = "ptr", feature = "rayon"))]
+pub use rayon_ext::{RayonGuard, lock_rayon};
+#[cfg(feature = "spin-compat")]
+pub use spin_compat::Pause;
+#[cfg(all(target_has_atomic = "ptr", feature = "split"))]
+pub use split::{GuardA, GuardB, SplitMutex};
+#[cfg(all(target_has_atomic = "ptr", feature = "tokio-blocking"))]
+pub use tokio_ext::lock_blocking;
+#[cfg(all(target_has_atomic = "ptr", feature = "watchdog"))]
+pub use watchdog::{StuckLockInfo, WatchdogHandle, spawn_watchdog};
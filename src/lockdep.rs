@@ -0,0 +1,130 @@
+//! Lock-ordering violation detection ("lockdep"-style), enabled via the `lockdep` feature.
+//!
+//! Each `Mutex` can be tagged with a lock *class* via `Mutex::set_lock_class`. This module tracks,
+//! per thread, the classes currently held and records the order in which classes are acquired
+//! relative to one another in a global graph. If a thread acquires class `B` while holding `A`,
+//! and some other thread (at any point, possibly long before) acquired `A` while holding `B`, that
+//! is a potential ABBA deadlock: the two orderings can't both be safe in general, even if this
+//! particular run never actually deadlocked. This is caught eagerly, at the point the inconsistent
+//! ordering is first observed, rather than only when a real deadlock occurs.
+//!
+//! This is purely a debugging aid built on top of the `Mutex`/`Guard` API; it has no bearing on
+//! the actual locking algorithm and adds overhead only when the `lockdep` feature is enabled.
+
+use std::boxed::Box;
+use std::cell::RefCell;
+use std::ptr;
+use std::sync::Mutex as StdMutex;
+use std::sync::Once;
+use std::vec::Vec;
+
+std::thread_local! {
+    static HELD: RefCell<Vec<&'static str>> = RefCell::new(Vec::new());
+}
+
+static EDGES_INIT: Once = Once::new();
+static mut EDGES: *const StdMutex<Vec<(&'static str, &'static str)>> = ptr::null();
+
+fn edges() -> &'static StdMutex<Vec<(&'static str, &'static str)>> {
+    unsafe {
+        EDGES_INIT.call_once(|| {
+            EDGES = Box::into_raw(Box::new(StdMutex::new(Vec::new())));
+        });
+        &*EDGES
+    }
+}
+
+/// Records that `class` is being acquired on the current thread, checking it against every class
+/// already held here for a previously-observed opposite ordering.
+///
+/// Panics if acquiring `class` while holding some `other` class is inconsistent with an ordering
+/// (`other` after `class`) recorded by an earlier acquisition, on this thread or another.
+pub fn on_acquire(class: &'static str) {
+    HELD.with(|held| {
+        for &already_held in held.borrow().iter() {
+            if already_held != class {
+                record_edge(already_held, class);
+            }
+        }
+        held.borrow_mut().push(class);
+    });
+}
+
+/// Records that `class` was released on the current thread.
+pub fn on_release(class: &'static str) {
+    HELD.with(|held| {
+        let mut held = held.borrow_mut();
+        if let Some(pos) = held.iter().rposition(|&c| c == class) {
+            held.remove(pos);
+        }
+    });
+}
+
+fn record_edge(before: &'static str, after: &'static str) {
+    let mut edges = edges().lock().unwrap();
+    if edges.contains(&(after, before)) {
+        // Release the edge registry before panicking, so the panic does not poison it for every
+        // later acquisition.
+        drop(edges);
+        panic!(
+            "lockdep: inconsistent lock ordering: `{}` acquired before `{}` here, but `{}` was \
+             previously observed acquired before `{}`",
+            before, after, after, before
+        );
+    }
+    if !edges.contains(&(before, after)) {
+        edges.push((before, after));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{on_acquire, on_release};
+
+    use std::panic;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    #[test]
+    fn consistent_ordering_does_not_panic() {
+        on_acquire("consistent-a");
+        on_acquire("consistent-b");
+        on_release("consistent-b");
+        on_release("consistent-a");
+
+        on_acquire("consistent-a");
+        on_acquire("consistent-b");
+        on_release("consistent-b");
+        on_release("consistent-a");
+    }
+
+    #[test]
+    fn inconsistent_ordering_across_threads_panics() {
+        // Have one thread establish `abba-a` before `abba-b`, then have this thread try the
+        // opposite order after the first thread has released both, so the violation is detected
+        // deterministically rather than racing on lock acquisition order.
+        let barrier = Arc::new(Barrier::new(2));
+        let other_barrier = barrier.clone();
+        let handle = thread::spawn(move || {
+            on_acquire("abba-a");
+            on_acquire("abba-b");
+            on_release("abba-b");
+            on_release("abba-a");
+            other_barrier.wait();
+        });
+        barrier.wait();
+        handle.join().unwrap();
+
+        let result = panic::catch_unwind(|| {
+            on_acquire("abba-b");
+            on_acquire("abba-a");
+        });
+        assert!(result.is_err(), "inconsistent ordering should have panicked");
+
+        // Clean up thread-local state so this test doesn't wedge later assertions if the panic
+        // is ever caught somewhere that reuses this thread (e.g. a test harness thread pool).
+        // The panicking `on_acquire("abba-a")` never pushed onto `HELD`, so only the first
+        // successful acquisition needs unwinding.
+        on_release("abba-b");
+    }
+}
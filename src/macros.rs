@@ -0,0 +1,74 @@
+//! `parking_lot`-style ergonomics for declaring `const`-initialized statics.
+
+/// Declares a `static` MCS `Mutex`, `const`-initialized.
+///
+/// Equivalent to `static $name: mcs::Mutex<$ty> = mcs::Mutex::new($value);`, provided as a
+/// shorthand for codebases with many static locks. Requires the `unstable` feature, since
+/// `Mutex::new` is only `const` under it.
+#[cfg(feature = "unstable")]
+#[macro_export]
+macro_rules! const_mutex {
+    ($name:ident: $ty:ty = $value:expr) => {
+        static $name: $crate::Mutex<$ty> = $crate::Mutex::new($value);
+    };
+}
+
+/// Declares a thread-local pool of `$count` `Slot`s, `const`-initialized.
+///
+/// `Mutex::lock` needs a `&mut Slot` per waiter, so a plain `static` array of `Slot`s (immutable)
+/// can't be locked with directly; this instead declares a `std::thread_local!` of
+/// `RefCell`-wrapped slots, each of which yields a `&mut Slot` via `borrow_mut()` for the
+/// duration of a closure passed to `$name.with(..)`. Requires the `unstable` and `std` features.
+#[cfg(all(feature = "unstable", feature = "std"))]
+#[macro_export]
+macro_rules! static_slots {
+    ($name:ident, $count:expr) => {
+        ::std::thread_local! {
+            static $name: [::std::cell::RefCell<$crate::Slot>; $count] =
+                [const { ::std::cell::RefCell::new($crate::Slot::new()) }; $count];
+        }
+    };
+}
+
+#[cfg(all(test, feature = "unstable", feature = "std"))]
+mod test {
+    const_mutex!(COUNTER: u32 = 0);
+    static_slots!(SLOTS, 4);
+
+    #[test]
+    fn test_const_mutex_and_static_slots_are_usable() {
+        SLOTS.with(|slots| {
+            let mut slot = slots[0].borrow_mut();
+            *COUNTER.lock(&mut slot) += 1;
+        });
+        SLOTS.with(|slots| {
+            let mut slot = slots[0].borrow_mut();
+            assert_eq!(*COUNTER.lock(&mut slot), 1);
+        });
+    }
+
+    // Repeatedly acquiring and releasing through the same pooled slot address, alternated with
+    // acquisitions through a *different* pooled slot in the same thread-local pool, is exactly the
+    // "slot address gets recycled" scenario a shared slot pool would introduce. This is safe here
+    // because each `Guard` for `slots[0]` is fully dropped (and its handoff resolved) before the
+    // next iteration reborrows `slots[0]`; see the comment on `Mutex`'s `queue` field for why that
+    // invariant, not a tagged pointer, is what actually rules out ABA on the release path.
+    #[test]
+    fn test_recycling_the_same_slot_repeatedly_stays_correct() {
+        const_mutex!(RECYCLE_COUNTER: u32 = 0);
+
+        for _ in 0..1000 {
+            SLOTS.with(|slots| {
+                let mut a = slots[0].borrow_mut();
+                let mut b = slots[1].borrow_mut();
+                *RECYCLE_COUNTER.lock(&mut a) += 1;
+                *RECYCLE_COUNTER.lock(&mut b) += 1;
+            });
+        }
+
+        SLOTS.with(|slots| {
+            let mut slot = slots[0].borrow_mut();
+            assert_eq!(*RECYCLE_COUNTER.lock(&mut slot), 2000);
+        });
+    }
+}
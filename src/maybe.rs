@@ -0,0 +1,103 @@
+//! Formalizes the "lock only sometimes" pattern: acquiring a lock conditionally while still
+//! managing the `Slot` correctly regardless of which branch is taken.
+//!
+//! An `Option<Guard<'a, T>>` built by hand works too, but `lock_maybe`/`MaybeGuard` read more
+//! clearly at the call site than a manual `if condition { Some(mutex.lock(slot)) } else { None }`,
+//! and keep the "was it actually acquired?" check next to the value instead of separate.
+
+use core::ops::{Deref, DerefMut};
+
+use crate::mutex::{Guard, Mutex, Slot};
+
+/// The result of `lock_maybe`: either a held `Guard`, or nothing if the condition was `false`.
+///
+/// `Deref`s to `Option<Guard<'a, T>>` rather than `Option<&T>`, since `Deref::deref` can only ever
+/// hand back a reference to its target, and `&Option<&T>` isn't the same type as `Option<&T>`;
+/// pattern-match on `*maybe_guard` (or call `.as_deref()` on it) to get from there to `Option<&T>`.
+pub struct MaybeGuard<'a, T: ?Sized>(Option<Guard<'a, T>>);
+
+/// Acquires `mutex` only if `condition` is `true`, returning a `MaybeGuard` either way.
+///
+/// `slot` is only actually used (and only needs to outlive the resulting guard) when `condition`
+/// is `true`; it's still taken unconditionally so callers don't need to branch themselves to
+/// decide whether a `Slot` is needed.
+pub fn lock_maybe<'a, T: ?Sized>(mutex: &'a Mutex<T>, slot: &'a mut Slot, condition: bool) -> MaybeGuard<'a, T> {
+    if condition {
+        MaybeGuard(Some(mutex.lock(slot)))
+    } else {
+        MaybeGuard(None)
+    }
+}
+
+impl<'a, T: ?Sized> MaybeGuard<'a, T> {
+    /// Returns whether the lock was actually acquired.
+    pub fn is_held(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+impl<'a, T: ?Sized> Deref for MaybeGuard<'a, T> {
+    type Target = Option<Guard<'a, T>>;
+    fn deref(&self) -> &Option<Guard<'a, T>> {
+        &self.0
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for MaybeGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Option<Guard<'a, T>> {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::lock_maybe;
+    use crate::mutex::{Mutex, Slot};
+
+    #[test]
+    fn test_lock_maybe_acquires_when_condition_is_true() {
+        let mutex = Mutex::new(41);
+        let mut slot = Slot::new();
+
+        let mut guard = lock_maybe(&mutex, &mut slot, true);
+        assert!(guard.is_held());
+        if let Some(inner) = &mut *guard {
+            **inner += 1;
+        }
+        drop(guard);
+
+        let mut slot = Slot::new();
+        assert_eq!(*mutex.lock(&mut slot), 42);
+    }
+
+    #[test]
+    fn test_lock_maybe_does_not_acquire_when_condition_is_false() {
+        let mutex = Mutex::new(0);
+        let mut slot = Slot::new();
+
+        let guard = lock_maybe(&mutex, &mut slot, false);
+        assert!(!guard.is_held());
+        assert!((*guard).is_none());
+        drop(guard);
+
+        // Not having acquired the lock means it must still be free.
+        let mut slot = Slot::new();
+        assert!(mutex.try_lock(&mut slot).is_ok());
+    }
+
+    #[test]
+    fn test_drop_releases_only_when_held() {
+        let mutex = Mutex::new(0);
+
+        let mut slot_a = Slot::new();
+        drop(lock_maybe(&mutex, &mut slot_a, false));
+
+        // If the not-held case had somehow still locked (or double-released) the mutex, this
+        // would either deadlock or panic.
+        let mut slot_b = Slot::new();
+        drop(lock_maybe(&mutex, &mut slot_b, true));
+
+        let mut slot_c = Slot::new();
+        assert!(mutex.try_lock(&mut slot_c).is_ok());
+    }
+}
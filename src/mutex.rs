@@ -1,14 +1,108 @@
+use core::borrow::{Borrow, BorrowMut};
 use core::cell::UnsafeCell;
-use core::ops::{Deref, DerefMut};
+use core::marker::PhantomData;
+use core::mem;
+#[cfg(feature = "lazy-init")]
+use core::mem::MaybeUninit;
+use core::ops::{ControlFlow, Deref, DerefMut, Index, IndexMut};
 use core::ptr;
-use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering, fence};
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering, fence};
 
-use pause::pause;
+#[cfg(feature = "allocator-api")]
+use std::alloc::Allocator;
+#[cfg(any(feature = "allocator-api", feature = "permit"))]
+use std::boxed::Box;
+#[cfg(any(feature = "metrics", feature = "watchdog"))]
+use std::string::{String, ToString};
+#[cfg(feature = "std")]
+use std::sync::Arc;
+#[cfg(feature = "backtrace")]
+use std::backtrace::Backtrace;
+#[cfg(any(feature = "diagnostics", feature = "lockdep", feature = "backtrace", feature = "fair", feature = "watchdog", all(feature = "reentrancy-check", debug_assertions)))]
+use std::sync::Mutex as StdMutex;
+#[cfg(feature = "lockdep")]
+use crate::lockdep;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+#[cfg(feature = "diagnostics")]
+use std::vec::Vec;
+#[cfg(any(feature = "fair", feature = "watchdog", all(feature = "reentrancy-check", debug_assertions)))]
+use std::thread::{self, ThreadId};
+
+use crate::pause::pause;
+#[cfg(feature = "stats")]
+use crate::reborrow::reborrow_mut;
+
+/// Issues a software prefetch for the cache line containing `*ptr`, hinting that it will be read
+/// soon.
+///
+/// Acquiring the lock is almost always immediately followed by touching the protected data, so
+/// issuing the prefetch as early as possible (right after acquisition, before returning the
+/// `Guard`) gives the memory system the most time to warm the cache line before the critical
+/// section actually reads it. This is a hint only: on targets without a known prefetch intrinsic
+/// it's a no-op, and even where it's implemented the hardware is free to ignore it.
+#[cfg(feature = "prefetch")]
+#[inline(always)]
+fn prefetch_read<T: ?Sized>(ptr: *const T) {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    unsafe {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::{_mm_prefetch, _MM_HINT_T0};
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+
+        _mm_prefetch(ptr as *const () as *const i8, _MM_HINT_T0);
+    }
+
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    {
+        let _ = ptr;
+    }
+}
+
+/// Appends one Prometheus counter (`# HELP`, `# TYPE ... counter`, and the sample line) to `out`.
+///
+/// Built by hand with `push_str`/`ToString::to_string` rather than `format!`/`write!`: this crate
+/// is `#![no_std]`, and `extern crate std;` alone doesn't bring those macros into scope.
+#[cfg(feature = "metrics")]
+fn push_counter(out: &mut String, metric_prefix: &str, metric_suffix: &str, help: &str, value: usize) {
+    out.push_str("# HELP ");
+    out.push_str(metric_prefix);
+    out.push('_');
+    out.push_str(metric_suffix);
+    out.push(' ');
+    out.push_str(help);
+    out.push('\n');
+    out.push_str("# TYPE ");
+    out.push_str(metric_prefix);
+    out.push('_');
+    out.push_str(metric_suffix);
+    out.push_str(" counter\n");
+    out.push_str(metric_prefix);
+    out.push('_');
+    out.push_str(metric_suffix);
+    out.push(' ');
+    out.push_str(&value.to_string());
+    out.push('\n');
+}
 
 pub struct Slot {
-    next: AtomicPtr<AtomicBool>
+    next: AtomicPtr<AtomicBool>,
+    #[cfg(feature = "diagnostics")]
+    label: Option<&'static str>,
+    #[cfg(all(feature = "raw-token", debug_assertions))]
+    raw_locked: AtomicBool
 }
 
+// The release path always runs on the thread that dropped the guard. Under
+// `same-thread-guard`, `Guard` carries a `*const ()` marker so that it cannot be sent to another
+// thread, guaranteeing the release happens on the thread that acquired the lock. Without the
+// feature the marker is a plain, `Send`-agnostic `PhantomData<()>` and imposes no restriction.
+#[cfg(feature = "same-thread-guard")]
+type NotSend = PhantomData<*const ()>;
+#[cfg(not(feature = "same-thread-guard"))]
+type NotSend = PhantomData<()>;
+
 /// An RAII implementation of a "scoped lock" of a mutex. When this structure is
 /// dropped (falls out of scope), the lock will be unlocked.
 ///
@@ -17,7 +111,8 @@ pub struct Slot {
 #[must_use]
 pub struct Guard<'a, T: ?Sized + 'a> {
     lock: &'a Mutex<T>,
-    slot: &'a Slot
+    slot: &'a Slot,
+    _not_send: NotSend
 }
 
 /// A mutual exclusion primitive useful for protecting shared data
@@ -69,11 +164,83 @@ pub struct Guard<'a, T: ?Sized + 'a> {
 ///
 /// rx.recv().unwrap();
 /// ```
+// The `repr-c` feature promises a stable layout with `queue` before `data` for the `ffi` shims'
+// callers. That promise does not extend to combining `repr-c` with the features that insert
+// extra fields between them, such as `diagnostics` (its `waiters` field), `lockdep`, `stats`,
+// `fair` or `watchdog`.
+//
+// A note on ABA safety of `queue`, prompted by a proposal to recycle `Slot`s across a shared pool:
+// the release path's `compare_exchange(queue, self.slot as *const _ as *mut _, null, ..)` compares
+// by raw address, so it would in principle be vulnerable to ABA if the exact address of `self`'s
+// own slot could reappear in `queue` between the read and the CAS. That can't happen today: the
+// `Guard` holds `slot: &'a Slot` for the whole of `Drop`, so nothing else can be mutating or
+// recycling that same memory as a *different* logical `Slot` while this CAS runs, and the
+// borrow checker enforces that no new acquisition reuses the address until this `Guard` (and
+// hence this borrow) is gone. So a version/generation tag on `queue` would guard a CAS that isn't
+// actually ABA-exposed under the invariants this crate currently upholds.
+//
+// The real hazard of slot recycling isn't this CAS: it's `unsafe { &*pred }` in `lock`, which
+// dereferences a raw `*mut Slot` obtained from an earlier `queue.swap`. If a *shared, cross-thread*
+// slot pool recycled a `Slot` while some other thread still held a stale `pred` pointer into it
+// (i.e. reused it before that thread's handoff had definitely completed), the dereference would be
+// a use-after-free/type-confusion regardless of any tag on `queue`, since the corruption happens
+// through `pred`, not through the tail pointer's CAS. A tagged `queue` pointer doesn't fix that.
+// The actual fix for a shared slot pool is deferred reclamation (only return a `Slot` to the pool
+// once every thread that could still be holding a `pred` into it has moved past that point, e.g.
+// via an epoch scheme) rather than a generation counter here. This crate's existing slot-reuse
+// helper (`static_slots!`) sidesteps the whole issue by keeping each pooled `Slot` thread-local, so
+// only the one thread that could ever see it as `pred` ever reuses it, and only after its `Guard`
+// has already been dropped.
+#[cfg_attr(feature = "repr-c", repr(C))]
 pub struct Mutex<T: ?Sized> {
     queue: AtomicPtr<Slot>,
+    #[cfg(feature = "diagnostics")]
+    waiters: StdMutex<Vec<&'static str>>,
+    #[cfg(feature = "lockdep")]
+    lock_class: StdMutex<Option<&'static str>>,
+    #[cfg(feature = "backtrace")]
+    last_acquire_backtrace: StdMutex<Option<Arc<Backtrace>>>,
+    #[cfg(feature = "stats")]
+    stat_acquisitions: AtomicUsize,
+    #[cfg(feature = "stats")]
+    stat_contended: AtomicUsize,
+    #[cfg(feature = "stats")]
+    stat_spins: AtomicUsize,
+    #[cfg(feature = "stats")]
+    stat_fast_path_hits: AtomicUsize,
+    #[cfg(feature = "stats")]
+    stat_fast_path_misses: AtomicUsize,
+    #[cfg(feature = "fair")]
+    fairness_owner: StdMutex<Option<ThreadId>>,
+    #[cfg(feature = "fair")]
+    fairness_streak: AtomicUsize,
+    #[cfg(feature = "fair")]
+    max_consecutive_same_thread: AtomicUsize,
+    #[cfg(feature = "first-acquire")]
+    first_acquire: AtomicBool,
+    #[cfg(all(feature = "reentrancy-check", debug_assertions))]
+    reentrancy_owner: StdMutex<Option<ThreadId>>,
+    #[cfg(all(feature = "lazy-init", debug_assertions))]
+    initialized: AtomicBool,
+    #[cfg(feature = "watchdog")]
+    held_since: StdMutex<Option<(Instant, ThreadId, Option<String>)>>,
+    #[cfg(feature = "versioned")]
+    version: AtomicUsize,
     data: UnsafeCell<T>
 }
 
+/// A snapshot of a mutex's contention counters, taken via `Mutex::stats`.
+#[cfg(feature = "stats")]
+#[derive(Clone, Copy, Debug)]
+pub struct MutexStats {
+    /// Total number of successful acquisitions (`lock`, `try_lock`, `try_lock_explicit`).
+    pub acquisitions: usize,
+    /// Of those, how many had to wait for another holder to release the lock first.
+    pub contended_acquisitions: usize,
+    /// Total number of spin iterations spent waiting across all contended acquisitions.
+    pub total_spins: usize
+}
+
 unsafe impl<T: Send> Sync for Mutex<T> { }
 unsafe impl<T: Send> Send for Mutex<T> { }
 
@@ -81,14 +248,72 @@ impl Slot {
     #[cfg(feature = "unstable")]
     pub const fn new() -> Slot {
         Slot {
-            next: AtomicPtr::new(ptr::null_mut())
+            next: AtomicPtr::new(ptr::null_mut()),
+            #[cfg(feature = "diagnostics")]
+            label: None,
+            #[cfg(all(feature = "raw-token", debug_assertions))]
+            raw_locked: AtomicBool::new(false)
         }
     }
 
     #[cfg(not(feature = "unstable"))]
     pub fn new() -> Slot {
         Slot {
-            next: AtomicPtr::new(ptr::null_mut())
+            next: AtomicPtr::new(ptr::null_mut()),
+            #[cfg(feature = "diagnostics")]
+            label: None,
+            #[cfg(all(feature = "raw-token", debug_assertions))]
+            raw_locked: AtomicBool::new(false)
+        }
+    }
+
+    /// Creates a new slot tagged with a human-readable label for diagnostics.
+    ///
+    /// The label shows up when dumping the mutex's current waiters via `Mutex::waiters`, which
+    /// is much easier to read than a raw slot address when debugging complex lock topologies.
+    #[cfg(feature = "diagnostics")]
+    pub fn labeled(label: &'static str) -> Slot {
+        Slot {
+            next: AtomicPtr::new(ptr::null_mut()),
+            label: Some(label),
+            #[cfg(all(feature = "raw-token", debug_assertions))]
+            raw_locked: AtomicBool::new(false)
+        }
+    }
+
+    /// Returns the label this slot was created with, if any.
+    #[cfg(feature = "diagnostics")]
+    pub fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    /// A default-constructed, unqueued slot, usable in const contexts such as `[Slot::DEFAULT; N]`
+    /// array literals.
+    ///
+    /// Equivalent to `Slot::new()`, but as an associated const rather than a function call; only
+    /// available under the `unstable` feature, since it requires `Slot::new` to be a `const fn`.
+    #[cfg(feature = "unstable")]
+    pub const DEFAULT: Slot = Slot::new();
+}
+
+impl Default for Slot {
+    fn default() -> Slot {
+        Slot::new()
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+struct WaiterGuard<'a> {
+    waiters: &'a StdMutex<Vec<&'static str>>,
+    label: &'static str
+}
+
+#[cfg(feature = "diagnostics")]
+impl<'a> Drop for WaiterGuard<'a> {
+    fn drop(&mut self) {
+        let mut waiters = self.waiters.lock().unwrap();
+        if let Some(pos) = waiters.iter().rposition(|&label| label == self.label) {
+            waiters.remove(pos);
         }
     }
 }
@@ -99,6 +324,38 @@ impl<T> Mutex<T> {
     pub const fn new(value: T) -> Mutex<T> {
         Mutex {
             queue: AtomicPtr::new(ptr::null_mut()),
+            #[cfg(feature = "diagnostics")]
+            waiters: StdMutex::new(Vec::new()),
+            #[cfg(feature = "lockdep")]
+            lock_class: StdMutex::new(None),
+            #[cfg(feature = "backtrace")]
+            last_acquire_backtrace: StdMutex::new(None),
+            #[cfg(feature = "stats")]
+            stat_acquisitions: AtomicUsize::new(0),
+            #[cfg(feature = "stats")]
+            stat_contended: AtomicUsize::new(0),
+            #[cfg(feature = "stats")]
+            stat_spins: AtomicUsize::new(0),
+            #[cfg(feature = "stats")]
+            stat_fast_path_hits: AtomicUsize::new(0),
+            #[cfg(feature = "stats")]
+            stat_fast_path_misses: AtomicUsize::new(0),
+            #[cfg(feature = "fair")]
+            fairness_owner: StdMutex::new(None),
+            #[cfg(feature = "fair")]
+            fairness_streak: AtomicUsize::new(0),
+            #[cfg(feature = "fair")]
+            max_consecutive_same_thread: AtomicUsize::new(usize::MAX),
+            #[cfg(feature = "first-acquire")]
+            first_acquire: AtomicBool::new(true),
+            #[cfg(all(feature = "reentrancy-check", debug_assertions))]
+            reentrancy_owner: StdMutex::new(None),
+            #[cfg(all(feature = "lazy-init", debug_assertions))]
+            initialized: AtomicBool::new(false),
+            #[cfg(feature = "watchdog")]
+            held_since: StdMutex::new(None),
+            #[cfg(feature = "versioned")]
+            version: AtomicUsize::new(0),
             data: UnsafeCell::new(value)
         }
     }
@@ -108,16 +365,103 @@ impl<T> Mutex<T> {
     pub fn new(value: T) -> Mutex<T> {
         Mutex {
             queue: AtomicPtr::new(ptr::null_mut()),
+            #[cfg(feature = "diagnostics")]
+            waiters: StdMutex::new(Vec::new()),
+            #[cfg(feature = "lockdep")]
+            lock_class: StdMutex::new(None),
+            #[cfg(feature = "backtrace")]
+            last_acquire_backtrace: StdMutex::new(None),
+            #[cfg(feature = "stats")]
+            stat_acquisitions: AtomicUsize::new(0),
+            #[cfg(feature = "stats")]
+            stat_contended: AtomicUsize::new(0),
+            #[cfg(feature = "stats")]
+            stat_spins: AtomicUsize::new(0),
+            #[cfg(feature = "stats")]
+            stat_fast_path_hits: AtomicUsize::new(0),
+            #[cfg(feature = "stats")]
+            stat_fast_path_misses: AtomicUsize::new(0),
+            #[cfg(feature = "fair")]
+            fairness_owner: StdMutex::new(None),
+            #[cfg(feature = "fair")]
+            fairness_streak: AtomicUsize::new(0),
+            #[cfg(feature = "fair")]
+            max_consecutive_same_thread: AtomicUsize::new(usize::MAX),
+            #[cfg(feature = "first-acquire")]
+            first_acquire: AtomicBool::new(true),
+            #[cfg(all(feature = "reentrancy-check", debug_assertions))]
+            reentrancy_owner: StdMutex::new(None),
+            #[cfg(all(feature = "lazy-init", debug_assertions))]
+            initialized: AtomicBool::new(false),
+            #[cfg(feature = "watchdog")]
+            held_since: StdMutex::new(None),
+            #[cfg(feature = "versioned")]
+            version: AtomicUsize::new(0),
             data: UnsafeCell::new(value)
         }
     }
 
+    /// Builds `N` mutexes, each protecting a value produced by calling `f` with its index.
+    ///
+    /// Like `core::array::from_fn`, but producing `Mutex`es rather than plain values, for shards
+    /// that each need distinct initial state rather than one value copied into every slot. Not
+    /// `const` (unlike `Mutex::new` under `unstable`), since a closure can't be called in a const
+    /// context.
+    pub fn from_fn_array<const N: usize>(mut f: impl FnMut(usize) -> T) -> [Mutex<T>; N] {
+        core::array::from_fn(|index| Mutex::new(f(index)))
+    }
+
     /// Consumes this mutex, returning the underlying data.
     pub fn into_inner(self) -> T {
         unsafe {
             self.data.into_inner()
         }
     }
+
+    /// Creates a new mutex in an unlocked state, boxed in `alloc` instead of the global allocator.
+    ///
+    /// `Mutex::new` places the mutex wherever the caller puts it (a local, a field, `Box::new`,
+    /// ...); it doesn't allocate on its own. This exists purely for the common case of wanting
+    /// that placement to itself be `Box::new_in`-style, e.g. to put a mutex in an arena alongside
+    /// the rest of a bump-allocated system.
+    ///
+    /// This only covers the `Mutex` node itself. The owned-guard helpers that additionally
+    /// heap-allocate a `Slot` (`arc_guard`, `coroutine`) still use the global allocator for that
+    /// `Slot`; threading a custom allocator through those as well would mean adding an allocator
+    /// type parameter to their public guard types, which is a larger, separate change.
+    #[cfg(feature = "allocator-api")]
+    pub fn new_in<A: Allocator>(value: T, alloc: A) -> Box<Mutex<T>, A> {
+        Box::new_in(Mutex::new(value), alloc)
+    }
+
+    /// Locks the mutex and, if `predicate` holds for the current value, replaces it with the
+    /// result of `new`, returning the replaced value.
+    ///
+    /// `new` is only invoked when the predicate holds, so it can be an expensive or side-effecting
+    /// computation without cost in the predicate-false case. Returns `None` without touching the
+    /// data if the predicate does not hold.
+    pub fn replace_if<P, F>(&self, slot: &mut Slot, predicate: P, new: F) -> Option<T>
+        where P: FnOnce(&T) -> bool, F: FnOnce() -> T
+    {
+        let mut guard = self.lock(slot);
+        if predicate(&*guard) {
+            Some(mem::replace(&mut *guard, new()))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Clone> Mutex<T> {
+    /// Locks the mutex inside `arc` and clones out its current value.
+    ///
+    /// Unlike `Arc::try_unwrap`, which only succeeds when `arc` is the sole reference and moves
+    /// the value out, this always succeeds by cloning the protected value instead, leaving the
+    /// `Arc` and the mutex it protects untouched.
+    pub fn extract_arc(arc: &Arc<Mutex<T>>, slot: &mut Slot) -> T {
+        arc.lock(slot).clone()
+    }
 }
 
 impl<T: ?Sized> Mutex<T> {
@@ -132,15 +476,127 @@ impl<T: ?Sized> Mutex<T> {
         slot.next = AtomicPtr::new(ptr::null_mut());
 
         if self.queue.compare_and_swap(ptr::null_mut(), slot, Ordering::AcqRel).is_null() {
+            #[cfg(feature = "lockdep")]
+            self.lockdep_on_acquire();
+            #[cfg(feature = "prefetch")]
+            prefetch_read(self.data.get());
+            #[cfg(feature = "backtrace")]
+            self.record_acquire_backtrace();
+            #[cfg(feature = "stats")]
+            self.stat_acquisitions.fetch_add(1, Ordering::Relaxed);
             Ok(Guard {
                 lock: self,
-                slot: slot
+                slot: slot,
+                _not_send: PhantomData
             })
         } else {
             Err(())
         }
     }
 
+    /// A single non-blocking acquisition attempt, wrapped in `ControlFlow` for composing with
+    /// retry loops: `Continue(())` means the lock was contended and should be retried, `Break`
+    /// carries the guard once it's acquired.
+    ///
+    /// Equivalent to `try_lock(slot).map_or(ControlFlow::Continue(()), ControlFlow::Break)`;
+    /// exists as its own method so callers building on `ControlFlow`-based control flow (e.g. a
+    /// `loop { match m.lock_or_retry(&mut slot) { ... } }`) don't need to spell that out.
+    pub fn lock_or_retry<'a>(&'a self, slot: &'a mut Slot) -> ControlFlow<Guard<'a, T>, ()> {
+        match self.try_lock(slot) {
+            Ok(guard) => ControlFlow::Break(guard),
+            Err(()) => ControlFlow::Continue(())
+        }
+    }
+
+    /// Like `try_lock`, but with explicit memory orderings for the underlying compare-and-swap,
+    /// mirroring `AtomicPtr::compare_exchange`.
+    ///
+    /// This is for experts building lock-free-adjacent structures on top of this mutex who need
+    /// to reason precisely about the acquire semantics. Misusing the orderings can break mutual
+    /// exclusion; in particular, `failure` must not be `Release` or `AcqRel`, since a failed
+    /// compare-and-swap does not establish a happens-before edge to release. Debug builds assert
+    /// against that specific misuse; it is not checked in release builds.
+    pub fn try_lock_explicit<'a>(&'a self, slot: &'a mut Slot, success: Ordering, failure: Ordering) -> Option<Guard<'a, T>> {
+        debug_assert!(
+            failure != Ordering::Release && failure != Ordering::AcqRel,
+            "failure ordering must not be Release or AcqRel"
+        );
+
+        slot.next = AtomicPtr::new(ptr::null_mut());
+
+        if self.queue.compare_exchange(ptr::null_mut(), slot, success, failure).is_ok() {
+            #[cfg(feature = "lockdep")]
+            self.lockdep_on_acquire();
+            #[cfg(feature = "prefetch")]
+            prefetch_read(self.data.get());
+            #[cfg(feature = "backtrace")]
+            self.record_acquire_backtrace();
+            #[cfg(feature = "stats")]
+            self.stat_acquisitions.fetch_add(1, Ordering::Relaxed);
+            Some(Guard {
+                lock: self,
+                slot: slot,
+                _not_send: PhantomData
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to acquire this lock without producing a `Guard`, returning whether it succeeded.
+    ///
+    /// For manual, FSM-driven acquire/release where even `RawLockToken` (see `Guard::into_raw`)
+    /// can't be carried between states because there's no room to store a Rust value at all, only
+    /// an external state tag. Prefer a `Guard` or `RawLockToken` whenever there's anywhere to put
+    /// one; this exists purely for the narrower case where there isn't. Does not block.
+    ///
+    /// A successful acquisition here must eventually be matched by exactly one `raw_unlock` call
+    /// on this same `slot`.
+    #[cfg(feature = "raw-token")]
+    pub fn raw_try_lock(&self, slot: &mut Slot) -> bool {
+        // `Guard` holds on to `slot` for its whole lifetime, including the debug-only `raw_locked`
+        // field the store below needs to touch, so a second access to `slot` while `guard` is
+        // alive (even one that's about to be forgotten) is rejected as conflicting with the first.
+        // Capturing `raw_locked` as a raw pointer before calling `try_lock` sidesteps that: the
+        // pointer carries no borrow of `slot`, so it can still be dereferenced once `guard` is the
+        // only thing left holding one.
+        #[cfg(debug_assertions)]
+        let raw_locked: *const AtomicBool = &slot.raw_locked;
+        match self.try_lock(slot) {
+            Ok(guard) => {
+                // Safety: `try_lock` just returned successfully, so `slot` (and the `raw_locked`
+                // field `raw_locked` points into) is still exclusively ours for as long as the
+                // resulting lock is held; `guard` itself is forgotten without ever touching this
+                // field, so there's no aliasing with anything else.
+                #[cfg(debug_assertions)]
+                unsafe { (*raw_locked).store(true, Ordering::Relaxed) };
+                mem::forget(guard);
+                true
+            }
+            Err(()) => false
+        }
+    }
+
+    /// Releases a lock previously acquired via a successful `raw_try_lock` call on this exact
+    /// `slot`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that `raw_try_lock(slot)` most recently returned `true` for this
+    /// mutex and slot, and that the lock hasn't already been released since. Violating this is
+    /// undefined behavior - most likely, releasing a lock another thread believes it still holds.
+    /// Debug builds additionally `debug_assert!` this via a per-`Slot` flag set by `raw_try_lock`
+    /// and cleared here; release builds perform no check and trust the caller completely.
+    #[cfg(feature = "raw-token")]
+    pub unsafe fn raw_unlock<'a>(&'a self, slot: &'a Slot) {
+        #[cfg(debug_assertions)]
+        debug_assert!(
+            slot.raw_locked.swap(false, Ordering::Relaxed),
+            "raw_unlock called without a matching successful raw_try_lock on this slot"
+        );
+        drop(Guard::from_raw_parts(self, slot));
+    }
+
     /// Acquires a mutex, blocking the current thread until it is able to do so.
     ///
     /// This function will block the local thread until it is available to acquire
@@ -148,21 +604,111 @@ impl<T: ?Sized> Mutex<T> {
     /// held. An RAII guard is returned to allow scoped unlock of the lock. When
     /// the guard goes out of scope, the mutex will be unlocked.
     pub fn lock<'a>(&'a self, slot: &'a mut Slot) -> Guard<'a, T> {
+        #[cfg(feature = "fair")]
+        self.yield_if_monopolizing();
+        #[cfg(all(feature = "reentrancy-check", debug_assertions))]
+        self.check_not_reentrant();
+
         slot.next = AtomicPtr::new(ptr::null_mut());
         let pred = self.queue.swap(slot, Ordering::AcqRel);
         if !pred.is_null() {
+            #[cfg(feature = "stats")]
+            self.stat_contended.fetch_add(1, Ordering::Relaxed);
             let pred = unsafe { &*pred };
             let locked = AtomicBool::new(true);
             pred.next.store(&locked as *const _ as *mut _, Ordering::Release);
+            #[cfg(feature = "diagnostics")]
+            let _waiter_guard = self.register_waiter(slot);
             while locked.load(Ordering::Relaxed) {
+                #[cfg(feature = "stats")]
+                self.stat_spins.fetch_add(1, Ordering::Relaxed);
                 pause();
             }
             fence(Ordering::Acquire);
         }
 
+        #[cfg(feature = "lockdep")]
+        self.lockdep_on_acquire();
+        #[cfg(feature = "prefetch")]
+        prefetch_read(self.data.get());
+        #[cfg(feature = "backtrace")]
+        self.record_acquire_backtrace();
+        #[cfg(feature = "stats")]
+        self.stat_acquisitions.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "fair")]
+        self.record_acquisition_for_fairness();
+        #[cfg(all(feature = "reentrancy-check", debug_assertions))]
+        {
+            *self.reentrancy_owner.lock().unwrap() = Some(thread::current().id());
+        }
+        #[cfg(feature = "watchdog")]
+        {
+            let this_thread = thread::current();
+            let name = this_thread.name().map(ToString::to_string);
+            *self.held_since.lock().unwrap() = Some((Instant::now(), this_thread.id(), name));
+        }
+
         Guard {
             lock: self,
-            slot: slot
+            slot: slot,
+            _not_send: PhantomData
+        }
+    }
+
+    /// Joins the queue for this mutex without blocking, returning a `Permit` that separates
+    /// "reserve a place in line" from "actually enter the critical section".
+    ///
+    /// Unlike `lock`, this always returns immediately, whether or not the mutex was contended;
+    /// call `Permit::wait` to block until it is actually this permit's turn. This is meant for
+    /// admission-control style schedulers that want to decide later (based on other state)
+    /// whether a caller should actually proceed into the critical section.
+    #[cfg(feature = "permit")]
+    pub fn enqueue<'a>(&'a self, slot: &'a mut Slot) -> Permit<'a, T> {
+        slot.next = AtomicPtr::new(ptr::null_mut());
+        let pred = self.queue.swap(slot, Ordering::AcqRel);
+        let locked = Box::new(AtomicBool::new(!pred.is_null()));
+        if !pred.is_null() {
+            #[cfg(feature = "stats")]
+            self.stat_contended.fetch_add(1, Ordering::Relaxed);
+            let pred = unsafe { &*pred };
+            pred.next.store(&*locked as *const AtomicBool as *mut AtomicBool, Ordering::Release);
+        }
+        Permit { lock: self, slot: Some(slot), locked: locked }
+    }
+
+    /// Acquires the mutex like `lock`, additionally reporting whether this was the very first
+    /// acquisition of this particular mutex.
+    ///
+    /// Returns `true` in the second element of the tuple exactly once across the lifetime of this
+    /// mutex, for whichever thread's acquisition wins the race to be first; every other call, on
+    /// any thread, sees `false`. Useful for mutex-scoped one-time initialization without a
+    /// separate `Once`, e.g. `let (mut guard, first) = m.lock_first(&mut slot); if first { ... }`.
+    #[cfg(feature = "first-acquire")]
+    pub fn lock_first<'a>(&'a self, slot: &'a mut Slot) -> (Guard<'a, T>, bool) {
+        let guard = self.lock(slot);
+        let first = self.first_acquire.swap(false, Ordering::AcqRel);
+        (guard, first)
+    }
+
+    /// Acquires the mutex like `lock`, additionally running `on_release` with a reference to
+    /// `slot` once the returned guard is dropped and its MCS dequeue has fully completed.
+    ///
+    /// Intended for pools that want to return a `Slot` to a freelist the instant it's safe to
+    /// reuse, rather than waiting for whatever scope holds the guard to end. `on_release` is only
+    /// ever called after the handoff to the next waiter (if any) is done, matching `Slot`'s
+    /// existing reuse rule (see `static_slots!`): a `Slot` is only safe to reuse once no queued
+    /// `pred` reference could still be pointing at it, which is exactly the point `Drop for Guard`
+    /// reaches right before returning.
+    #[cfg(feature = "release-hook")]
+    pub fn lock_with_release_hook<'a, F>(&'a self, slot: &'a mut Slot, on_release: F) -> HookedGuard<'a, T, F>
+        where F: FnOnce(&Slot)
+    {
+        let guard = self.lock(slot);
+        let slot_ptr: *const Slot = guard.slot;
+        HookedGuard {
+            guard: mem::ManuallyDrop::new(guard),
+            slot: slot_ptr,
+            on_release: Some(on_release)
         }
     }
 
@@ -173,158 +719,1133 @@ impl<T: ?Sized> Mutex<T> {
     pub fn get_mut(&mut self) -> &mut T {
         unsafe { &mut *self.data.get() }
     }
-}
 
-impl<'a, T: ?Sized> Deref for Guard<'a, T> {
-    type Target = T;
-    fn deref(&self) -> &T {
-        unsafe { &*self.lock.data.get() }
+    /// Returns a mutable reference to the underlying data without locking or proving exclusivity
+    /// through the type system.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that no other reference (mutable or shared, locked or not) to
+    /// the protected data is alive for the duration of the returned borrow. This is stricter than
+    /// "no other thread happens to be holding the lock right now": it also rules out any other
+    /// outstanding `Guard`, `get_mut` borrow, or another `get_mut_unchecked` borrow.
+    #[allow(clippy::mut_from_ref)] // the whole point of this function; safety is on the caller.
+    pub unsafe fn get_mut_unchecked(&self) -> &mut T {
+        &mut *self.data.get()
     }
-}
 
-impl<'a, T: ?Sized> DerefMut for Guard<'a, T> {
-    fn deref_mut(&mut self) -> &mut T {
-        unsafe { &mut *self.lock.data.get() }
+    /// Returns a mutable reference to the underlying data if the mutex is provably uncontended
+    /// and unlocked, or `None` otherwise.
+    ///
+    /// Like `get_mut`, this proves exclusivity through `&mut self`, so no other thread can touch
+    /// the queue while it runs. It differs from `get_mut` only in checking the queue first: the
+    /// queue is non-null here only when a lock was taken and never released through a `Guard`
+    /// (e.g. a forgotten guard, `raw_try_lock`, or the `ffi` shims), and then this returns `None`
+    /// instead of handing out the data.
+    pub fn try_get_mut(&mut self) -> Option<&mut T> {
+        if self.queue.load(Ordering::Relaxed).is_null() {
+            Some(self.get_mut())
+        } else {
+            None
+        }
     }
-}
-
-// Unforturnately, since just putting attributes on generic parameters is unstable, we have to duplicate the whole Drop impl
-#[cfg(feature = "unstable")]
-unsafe impl<'a, #[may_dangle] T: ?Sized> Drop for Guard<'a, T> {
-    fn drop(&mut self) {
-        let mut succ = self.slot.next.load(Ordering::Relaxed);
-        if succ.is_null() {
-            // No one has registered as waiting.
-            if self.lock.queue.compare_exchange(self.slot as *const _ as *mut _, ptr::null_mut(), Ordering::Release, Ordering::Relaxed).is_ok() {
-                // No one was waiting.
-                return;
-            }
 
-            // Some thread is waiting, but hasn't registered yet. Spin waiting for them to register themselves.
-            loop {
-                succ = self.slot.next.load(Ordering::Relaxed);
-                if !succ.is_null() {
-                    break;
-                }
-                pause();
-            }
-        }
+    /// Returns a raw pointer to the protected data, bypassing locking.
+    ///
+    /// For internal use by modules (such as `ffi`) that manage exclusive access through their
+    /// own raw pointer discipline instead of a `Guard`.
+    pub(crate) fn data_ptr(&self) -> *mut T {
+        self.data.get()
+    }
 
-        // Announce to the next waiter that the lock is free.
-        fence(Ordering::Acquire);
-        let succ = unsafe { &*succ };
-        succ.store(false, Ordering::Release);
+    /// Returns how long this mutex has been continuously held, and by which thread (its id, and
+    /// its name if it had one set at the moment of acquisition), if it is currently held.
+    ///
+    /// For internal use by the `watchdog` module, which needs this without going through a
+    /// `Guard` (it must be callable from a thread that doesn't hold the lock).
+    #[cfg(feature = "watchdog")]
+    pub(crate) fn held_since(&self) -> Option<(Instant, ThreadId, Option<String>)> {
+        self.held_since.lock().unwrap().clone()
     }
-}
 
-#[cfg(not(feature = "unstable"))]
-impl<'a, T: ?Sized> Drop for Guard<'a, T> {
-    fn drop(&mut self) {
-        let mut succ = self.slot.next.load(Ordering::Relaxed);
-        if succ.is_null() {
-            // No one has registered as waiting.
-            if self.lock.queue.compare_exchange(self.slot as *const _ as *mut _, ptr::null_mut(), Ordering::Release, Ordering::Relaxed).is_ok() {
-                // No one was waiting.
-                return;
-            }
+    /// Returns the labels of the slots currently blocked waiting for this mutex.
+    ///
+    /// Waiters created via `Slot::new` (unlabeled) do not appear. The order is unspecified and
+    /// the result is a snapshot that may already be stale by the time it's returned.
+    #[cfg(feature = "diagnostics")]
+    pub fn waiters(&self) -> Vec<&'static str> {
+        self.waiters.lock().unwrap().clone()
+    }
 
-            // Some thread is waiting, but hasn't registered yet. Spin waiting for them to register themselves.
-            loop {
-                succ = self.slot.next.load(Ordering::Relaxed);
-                if !succ.is_null() {
-                    break;
-                }
-                pause();
-            }
-        }
+    #[cfg(feature = "diagnostics")]
+    fn register_waiter<'a>(&'a self, slot: &Slot) -> Option<WaiterGuard<'a>> {
+        let label = slot.label?;
+        self.waiters.lock().unwrap().push(label);
+        Some(WaiterGuard { waiters: &self.waiters, label: label })
+    }
 
-        // Announce to the next waiter that the lock is free.
-        fence(Ordering::Acquire);
-        let succ = unsafe { &*succ };
-        succ.store(false, Ordering::Release);
+    /// Tags this mutex with a lock class for lock-ordering ("lockdep") checking.
+    ///
+    /// Once set, every `lock`, `try_lock`, and `try_lock_explicit` call on this mutex is checked
+    /// against the classes already held by the current thread; acquiring classes in an order that
+    /// is inconsistent with a previously observed acquisition (on any thread) panics with an ABBA
+    /// deadlock warning. Mutexes without a class set are not tracked.
+    #[cfg(feature = "lockdep")]
+    pub fn set_lock_class(&self, class: &'static str) {
+        *self.lock_class.lock().unwrap() = Some(class);
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::{Mutex, Slot};
+    #[cfg(feature = "lockdep")]
+    fn lockdep_on_acquire(&self) {
+        if let Some(class) = *self.lock_class.lock().unwrap() {
+            lockdep::on_acquire(class);
+        }
+    }
 
-    // Mostly stoled from the Rust standard Mutex implementation's tests, so
+    #[cfg(feature = "lockdep")]
+    fn lockdep_on_release(&self) {
+        if let Some(class) = *self.lock_class.lock().unwrap() {
+            lockdep::on_release(class);
+        }
+    }
 
-    // Copyright 2014 The Rust Project Developers. See the COPYRIGHT
-    // file at http://rust-lang.org/COPYRIGHT.
-    //
-    // Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
-    // http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
-    // <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
-    // option. This file may not be copied, modified, or distributed
-    // except according to those terms.
+    /// Returns the backtrace captured at the most recent successful acquisition of this mutex, if
+    /// any acquisition has happened yet.
+    ///
+    /// Returned as `Arc<Backtrace>` rather than an owned `Backtrace`, since `Backtrace` itself
+    /// doesn't implement `Clone`; the `Arc` lets this be called repeatedly (e.g. from a watchdog
+    /// thread polling a possibly-stuck holder) without re-capturing or fighting over ownership of
+    /// the stored backtrace.
+    #[cfg(feature = "backtrace")]
+    pub fn last_acquire_backtrace(&self) -> Option<Arc<Backtrace>> {
+        self.last_acquire_backtrace.lock().unwrap().clone()
+    }
 
-    use std::sync::Arc;
-    use std::sync::mpsc::channel;
-    use std::sync::atomic::{AtomicUsize, Ordering};
-    use std::thread;
+    #[cfg(feature = "backtrace")]
+    fn record_acquire_backtrace(&self) {
+        *self.last_acquire_backtrace.lock().unwrap() = Some(Arc::new(Backtrace::force_capture()));
+    }
 
-    #[derive(Eq, PartialEq, Debug)]
-    struct NonCopy(i32);
+    /// Sets how many times in a row `lock` may be re-acquired by the same thread before it starts
+    /// yielding to give other, queueing threads a chance to go first.
+    ///
+    /// This is the one configurable piece of the `fair` feature's overall fairness guarantee; the
+    /// other two hold unconditionally with no configuration needed:
+    ///
+    /// - **No barging.** The MCS queue itself never reorders waiters: a thread's position is fixed
+    ///   the instant it swaps itself onto `queue`, so a thread that starts waiting after another is
+    ///   already queued can never acquire the lock ahead of it. This is inherent to the algorithm,
+    ///   not something this feature adds.
+    /// - **FIFO unlock.** Release always hands off to whichever `Slot` registered as `succ` first
+    ///   (see `Drop for Guard`); there is no "fast release to a fresh acquirer" path that could skip
+    ///   an already-registered waiter.
+    /// - **Bounded re-locking.** FIFO ordering through the queue only protects threads that are
+    ///   actually queued: a thread that releases and immediately calls `lock` again can win the
+    ///   race to re-enqueue before anyone else joins the (momentarily empty) queue, monopolizing
+    ///   the lock indefinitely. This method closes that gap: once a thread has acquired the lock
+    ///   `max` times in a row with no other thread having acquired it in between, every further
+    ///   `lock` call from it first yields via `thread::yield_now`, giving another thread queued (or
+    ///   about to queue) a chance to run and enqueue first.
+    ///
+    /// `thread::yield_now` is only a scheduling hint, so the same thread can still win the lock
+    /// again after yielding: `max` is not a hard limit on how many times one thread acquires the
+    /// lock while another is waiting. `test_fairness_bypass_bound_measured_under_heavy_contention`
+    /// measures the longest same-thread streak under contention and asserts that it stays within
+    /// a fixed multiple of `max`. Disabled by default (equivalent to `usize::MAX`).
+    #[cfg(feature = "fair")]
+    pub fn set_max_consecutive_same_thread(&self, max: usize) {
+        self.max_consecutive_same_thread.store(max, Ordering::Relaxed);
+    }
 
-    #[test]
-    fn smoke() {
-        let mut slot = Slot::new();
-        let m = Mutex::new(());
-        drop(m.lock(&mut slot));
-        drop(m.lock(&mut slot));
+    #[cfg(feature = "fair")]
+    fn yield_if_monopolizing(&self) {
+        let max = self.max_consecutive_same_thread.load(Ordering::Relaxed);
+        let owner = self.fairness_owner.lock().unwrap();
+        if *owner == Some(thread::current().id()) && self.fairness_streak.load(Ordering::Relaxed) >= max {
+            drop(owner);
+            thread::yield_now();
+        }
     }
 
-    #[test]
-    fn lots_and_lots() {
-        lazy_static! {
-            static ref LOCK: Mutex<u32> = Mutex::new(0);
+    #[cfg(feature = "fair")]
+    fn record_acquisition_for_fairness(&self) {
+        let this_thread = thread::current().id();
+        let mut owner = self.fairness_owner.lock().unwrap();
+        if *owner == Some(this_thread) {
+            self.fairness_streak.fetch_add(1, Ordering::Relaxed);
+        } else {
+            *owner = Some(this_thread);
+            self.fairness_streak.store(1, Ordering::Relaxed);
         }
+    }
 
-        const ITERS: u32 = 1000;
-        const CONCURRENCY: u32 = 3;
+    /// Panics if the current thread already holds this mutex, instead of letting `lock` spin
+    /// forever waiting for a release that can never happen.
+    ///
+    /// This only tracks a single owning `ThreadId`, not a full reentrancy count, so it can't
+    /// support actually re-entering the lock (see the `ReentrantMutex`-style wrapper for that);
+    /// it exists purely to turn an easy self-deadlock bug into an immediate, debuggable panic.
+    /// Compiled in only under `debug_assertions`, so it costs nothing in release builds.
+    #[cfg(all(feature = "reentrancy-check", debug_assertions))]
+    fn check_not_reentrant(&self) {
+        let owner = self.reentrancy_owner.lock().unwrap();
+        if *owner == Some(thread::current().id()) {
+            drop(owner);
+            // Named via `std::thread::Builder::name`, when available, so the panic message
+            // points at a human-readable thread rather than an opaque `ThreadId`.
+            match thread::current().name() {
+                Some(name) => panic!("reentrant lock attempt: thread `{}` already holds this Mutex", name),
+                None => panic!("reentrant lock attempt: current thread already holds this Mutex")
+            }
+        }
+    }
 
-        fn inc() {
-            let mut slot = Slot::new();
-            for _ in 0..ITERS {
-                let mut g = LOCK.lock(&mut slot);
-                *g += 1;
+    /// Attempts the uncontended CAS-to-null fast path (like `try_lock`) first, only falling back
+    /// to `lock`'s swap-and-queue slow path if that fails, and records which path was taken.
+    ///
+    /// This exists purely for tuning: pair it with `fast_path_ratio` to measure, for a given
+    /// workload, what fraction of acquisitions could have been satisfied by a cheap CAS versus how
+    /// many actually needed to join the queue, without having to instrument the call sites
+    /// yourself. Behaves identically to plain `lock` otherwise.
+    #[cfg(feature = "stats")]
+    pub fn lock_fast_path<'a>(&'a self, slot: &'a mut Slot) -> Guard<'a, T> {
+        // `try_lock` ties its returned `Guard`'s lifetime to `slot`'s own, so falling back to
+        // `lock` on the same `slot` needs reborrowing it for that same `'a` a second time; see
+        // `reborrow_mut` for why that's sound despite the borrow checker not seeing it itself.
+        let slot: *mut Slot = slot;
+        match self.try_lock(unsafe { reborrow_mut(slot) }) {
+            Ok(guard) => {
+                self.stat_fast_path_hits.fetch_add(1, Ordering::Relaxed);
+                guard
             }
-        };
+            Err(()) => {
+                self.stat_fast_path_misses.fetch_add(1, Ordering::Relaxed);
+                self.lock(unsafe { reborrow_mut(slot) })
+            }
+        }
+    }
 
-        let (tx, rx) = channel();
-        for _ in 0..CONCURRENCY {
-            let tx2 = tx.clone();
-            thread::spawn(move|| { inc(); tx2.send(()).unwrap(); });
-            let tx2 = tx.clone();
-            thread::spawn(move|| { inc(); tx2.send(()).unwrap(); });
+    /// Returns the fraction of `lock_fast_path` calls made on this mutex so far that hit the
+    /// uncontended CAS fast path rather than falling back to the queue, as a value in `[0.0,
+    /// 1.0]`.
+    ///
+    /// Returns `0.0` if `lock_fast_path` has never been called on this mutex, rather than `NaN`
+    /// from a `0.0 / 0.0` division.
+    #[cfg(feature = "stats")]
+    pub fn fast_path_ratio(&self) -> f64 {
+        let hits = self.stat_fast_path_hits.load(Ordering::Relaxed) as f64;
+        let misses = self.stat_fast_path_misses.load(Ordering::Relaxed) as f64;
+        let total = hits + misses;
+        if total == 0.0 {
+            0.0
+        } else {
+            hits / total
         }
+    }
 
-        drop(tx);
-        for _ in 0..2 * CONCURRENCY {
-            rx.recv().unwrap();
+    /// Returns a snapshot of this mutex's contention counters.
+    ///
+    /// Counters are tracked with `Relaxed` atomics purely for diagnostics; the snapshot returned
+    /// here isn't atomic across the three fields, so treat it as an approximation under
+    /// concurrent load rather than a consistent point-in-time reading.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> MutexStats {
+        MutexStats {
+            acquisitions: self.stat_acquisitions.load(Ordering::Relaxed),
+            contended_acquisitions: self.stat_contended.load(Ordering::Relaxed),
+            total_spins: self.stat_spins.load(Ordering::Relaxed)
         }
-        let mut slot = Slot::new();
-        assert_eq!(*LOCK.lock(&mut slot), ITERS * CONCURRENCY * 2);
     }
 
-    #[test]
-    fn try_lock() {
-        let mut slot = Slot::new();
-        let m = Mutex::new(());
-        *m.try_lock(&mut slot).unwrap() = ();
+    /// Renders this mutex's contention counters as Prometheus text-exposition metrics.
+    ///
+    /// `name` is used as the metric name prefix, e.g. `export_metrics("mymutex")` produces
+    /// `mymutex_acquisitions_total`, `mymutex_contended_acquisitions_total`, and
+    /// `mymutex_spins_total` counters, each with a `# HELP` and `# TYPE ... counter` line.
+    #[cfg(feature = "metrics")]
+    pub fn export_metrics(&self, name: &str) -> String {
+        let stats = self.stats();
+        let mut out = String::new();
+        push_counter(
+            &mut out,
+            name,
+            "acquisitions_total",
+            "Total number of successful lock acquisitions.",
+            stats.acquisitions
+        );
+        push_counter(
+            &mut out,
+            name,
+            "contended_acquisitions_total",
+            "Number of acquisitions that had to wait for another holder to release the lock.",
+            stats.contended_acquisitions
+        );
+        push_counter(
+            &mut out,
+            name,
+            "spins_total",
+            "Total number of spin iterations spent waiting across all contended acquisitions.",
+            stats.total_spins
+        );
+        out
     }
 
-    #[test]
-    fn test_into_inner() {
-        let m = Mutex::new(NonCopy(10));
-        assert_eq!(m.into_inner(), NonCopy(10));
+    /// Acquires the mutex, blocking until it is available, and runs `f` with exclusive access to
+    /// the protected data, timing how long the critical section is held.
+    ///
+    /// If the critical section runs for longer than `warn_after`, `on_slow` is called with the
+    /// actual hold duration once the lock has been released. This is meant to catch critical
+    /// sections that unexpectedly run long and starve other waiters; `on_slow` is not called from
+    /// within the critical section, so it may itself be slow without extending the hold time.
+    #[cfg(feature = "std")]
+    pub fn lock_timed<'a, R, F, S>(&'a self, slot: &'a mut Slot, warn_after: Duration, f: F, on_slow: S) -> R
+        where F: FnOnce(&mut T) -> R, S: FnOnce(Duration)
+    {
+        let start;
+        let result = {
+            let mut guard = self.lock(slot);
+            start = Instant::now();
+            f(&mut *guard)
+        };
+        let elapsed = start.elapsed();
+        if elapsed > warn_after {
+            on_slow(elapsed);
+        }
+        result
     }
 
-    #[test]
-    fn test_into_inner_drop() {
-        struct Foo(Arc<AtomicUsize>);
+    /// Acquires the mutex, blocking until it is available, and returns a guard that reports its
+    /// own hold time to `sink` when it is dropped.
+    ///
+    /// Unlike `lock_timed`, which only calls its callback once a threshold is exceeded, this
+    /// reports every acquisition unconditionally, making it a building block for latency
+    /// histograms rather than a slow-section warning. As with `lock_timed`'s `on_slow`, `sink`
+    /// runs after the lock has already been released, so it may itself be slow without extending
+    /// the reported hold time.
+    #[cfg(feature = "std")]
+    pub fn lock_measured<'a, F>(&'a self, slot: &'a mut Slot, sink: F) -> MeasuredGuard<'a, T, F>
+        where F: FnOnce(Duration)
+    {
+        let guard = self.lock(slot);
+        let start = Instant::now();
+        MeasuredGuard { guard: mem::ManuallyDrop::new(guard), start: start, sink: Some(sink) }
+    }
+
+    /// Returns this mutex's current version counter.
+    ///
+    /// The counter starts at `0` and is incremented (with `Release` ordering) every time a guard
+    /// is dropped, whether or not the protected data was actually mutated through it: this mutex
+    /// can't distinguish a `DerefMut` borrow that changed something from one that didn't, so it
+    /// conservatively treats every release as a possible change. Pair with `lock_if_version` to
+    /// detect, after doing some work without the lock held, whether anyone touched the data in the
+    /// meantime.
+    #[cfg(feature = "versioned")]
+    pub fn version(&self) -> usize {
+        self.version.load(Ordering::Acquire)
+    }
+
+    /// Acquires the mutex, but only if its version counter still matches `expected_version`;
+    /// otherwise releases it immediately and returns `None`.
+    ///
+    /// This is the check half of an optimistic-concurrency pattern: read `version()` before doing
+    /// some expensive work without the lock held, then call `lock_if_version` with that reading
+    /// once the work is done. `None` means the protected data may have changed underneath the
+    /// caller and the work should be redone (or discarded); `Some` means it's safe to act on the
+    /// work while holding the returned guard, since the version hasn't moved since it was read.
+    #[cfg(feature = "versioned")]
+    pub fn lock_if_version<'a>(&'a self, slot: &'a mut Slot, expected_version: usize) -> Option<Guard<'a, T>> {
+        let guard = self.lock(slot);
+        if self.version.load(Ordering::Acquire) == expected_version {
+            Some(guard)
+        } else {
+            None
+        }
+    }
+
+    /// Acquires the mutex, blocking until it is available, and runs `f` with exclusive access to
+    /// the protected data, aborting the process if `f` panics instead of unwinding through the
+    /// release.
+    ///
+    /// Unwinding across a `Guard`'s `Drop` is normally sound (the lock is still released; the
+    /// protected data is merely left in whatever state `f` left it in before panicking), but some
+    /// callers hold this mutex across a boundary where unwinding through it would itself be
+    /// unsound or otherwise catastrophic (e.g. across an `extern "C"` frame, or across a
+    /// hand-rolled continuation that assumes the critical section runs to completion). For those
+    /// callers, a panic while holding the lock is a bug they've already decided should be
+    /// fail-fast rather than recoverable: **`f` panicking here aborts the whole process** via
+    /// `std::process::abort`, unconditionally, with no chance for any `catch_unwind` up the stack
+    /// (including this crate's own) to observe or handle it.
+    #[cfg(feature = "std")]
+    pub fn lock_no_unwind<'a, R, F>(&'a self, slot: &'a mut Slot, f: F) -> R
+        where F: FnOnce(&mut T) -> R
+    {
+        struct AbortOnUnwind;
+        impl Drop for AbortOnUnwind {
+            fn drop(&mut self) {
+                if ::std::thread::panicking() {
+                    ::std::process::abort();
+                }
+            }
+        }
+
+        let mut guard = self.lock(slot);
+        let _abort_on_unwind = AbortOnUnwind;
+        f(&mut *guard)
+    }
+}
+
+/// Helpers for the common "lazily-constructed global protected by a lock" pattern, where the
+/// protected value doesn't exist yet until the first thread to get there constructs it under the
+/// lock.
+#[cfg(feature = "lazy-init")]
+impl<T> Mutex<MaybeUninit<T>> {
+    /// Locks the mutex and writes `value` into it, overwriting whatever was there before (dropping
+    /// it first if it was already initialized would require tracking that at the type level, which
+    /// this doesn't do; use this only for genuinely first-time initialization).
+    pub fn write_init<'a>(&'a self, slot: &'a mut Slot, value: T) {
+        self.lock(slot).write(value);
+        #[cfg(debug_assertions)]
+        self.initialized.store(true, Ordering::Release);
+    }
+
+    /// Locks the mutex and returns a guard through which the initialized value can be read.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that `write_init` has already completed on this mutex before
+    /// this is called; reading an uninitialized value is undefined behavior. In debug builds,
+    /// this is checked with a `debug_assert!` against whether `write_init` has ever run; release
+    /// builds trust the caller entirely and perform no check.
+    pub unsafe fn assume_init_ref<'a>(&'a self, slot: &'a mut Slot) -> MaybeUninitGuard<'a, T> {
+        #[cfg(debug_assertions)]
+        debug_assert!(
+            self.initialized.load(Ordering::Acquire),
+            "assume_init_ref called before write_init"
+        );
+        MaybeUninitGuard(self.lock(slot))
+    }
+}
+
+/// An RAII guard over an initialized `Mutex<MaybeUninit<T>>`, returned by `assume_init_ref`.
+#[cfg(feature = "lazy-init")]
+#[must_use]
+pub struct MaybeUninitGuard<'a, T: 'a>(Guard<'a, MaybeUninit<T>>);
+
+#[cfg(feature = "lazy-init")]
+impl<'a, T> Deref for MaybeUninitGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // Safety: constructing this guard already required the caller to uphold
+        // `assume_init_ref`'s safety contract that the value is initialized.
+        unsafe { self.0.assume_init_ref() }
+    }
+}
+
+/// An RAII guard returned by `Mutex::lock_with_release_hook`.
+///
+/// Dropping this releases the underlying lock (running the MCS dequeue exactly like a plain
+/// `Guard`) and then invokes the release hook with a reference to the `Slot`, in that order, so
+/// the hook only ever sees a `Slot` that is truly done being referenced by the queue.
+#[cfg(feature = "release-hook")]
+#[must_use]
+pub struct HookedGuard<'a, T: ?Sized + 'a, F: FnOnce(&Slot)> {
+    guard: mem::ManuallyDrop<Guard<'a, T>>,
+    slot: *const Slot,
+    on_release: Option<F>
+}
+
+#[cfg(feature = "release-hook")]
+impl<'a, T: ?Sized, F: FnOnce(&Slot)> Deref for HookedGuard<'a, T, F> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &*self.guard
+    }
+}
+
+#[cfg(feature = "release-hook")]
+impl<'a, T: ?Sized, F: FnOnce(&Slot)> DerefMut for HookedGuard<'a, T, F> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut *self.guard
+    }
+}
+
+#[cfg(feature = "release-hook")]
+impl<'a, T: ?Sized, F: FnOnce(&Slot)> Drop for HookedGuard<'a, T, F> {
+    fn drop(&mut self) {
+        // Safety: `guard` is never accessed again after this, matching `ManuallyDrop::drop`'s
+        // requirement, and this is the only place that drops it.
+        unsafe { mem::ManuallyDrop::drop(&mut self.guard); }
+        if let Some(on_release) = self.on_release.take() {
+            // Safety: `self.slot` was taken from the just-dropped `Guard`'s own `&'a Slot`, and
+            // the dequeue `Guard::drop` just performed is what makes it safe to hand a reference
+            // to this `Slot` to anyone else, including this hook.
+            on_release(unsafe { &*self.slot });
+        }
+    }
+}
+
+/// An RAII guard returned by `Mutex::lock_measured`.
+///
+/// Dropping this releases the underlying lock and then invokes the sink with the section's hold
+/// time (from acquisition to release), in that order, so the sink itself is never counted as part
+/// of the hold time it reports.
+#[cfg(feature = "std")]
+#[must_use]
+pub struct MeasuredGuard<'a, T: ?Sized + 'a, F: FnOnce(Duration)> {
+    guard: mem::ManuallyDrop<Guard<'a, T>>,
+    start: Instant,
+    sink: Option<F>
+}
+
+#[cfg(feature = "std")]
+impl<'a, T: ?Sized, F: FnOnce(Duration)> Deref for MeasuredGuard<'a, T, F> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &*self.guard
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, T: ?Sized, F: FnOnce(Duration)> DerefMut for MeasuredGuard<'a, T, F> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut *self.guard
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, T: ?Sized, F: FnOnce(Duration)> Drop for MeasuredGuard<'a, T, F> {
+    fn drop(&mut self) {
+        // Safety: `guard` is never accessed again after this, matching `ManuallyDrop::drop`'s
+        // requirement, and this is the only place that drops it.
+        unsafe { mem::ManuallyDrop::drop(&mut self.guard); }
+        if let Some(sink) = self.sink.take() {
+            sink(self.start.elapsed());
+        }
+    }
+}
+
+/// A reserved place in a mutex's queue, returned by `Mutex::enqueue`.
+///
+/// Call `wait` to block until it is this permit's turn and obtain the usual `Guard`, or `cancel`
+/// to abandon the acquisition. See `cancel`'s documentation for an important caveat: this queue
+/// doesn't track predecessors, so an already-queued (contended) permit can't actually splice
+/// itself out early, and `cancel` still waits for its turn in that case.
+#[cfg(feature = "permit")]
+#[must_use]
+pub struct Permit<'a, T: ?Sized + 'a> {
+    lock: &'a Mutex<T>,
+    slot: Option<&'a mut Slot>,
+    locked: Box<AtomicBool>
+}
+
+#[cfg(feature = "permit")]
+impl<'a, T: ?Sized> Permit<'a, T> {
+    /// Blocks until this permit reaches the head of the queue, then returns a `Guard` for the
+    /// critical section, exactly as `Mutex::lock` would have.
+    pub fn wait(mut self) -> Guard<'a, T> {
+        self.wait_for_turn().expect("Permit::wait called on an already-resolved permit")
+    }
+
+    /// Abandons this acquisition: the critical section is never exposed to the caller.
+    ///
+    /// If this permit hadn't yet queued behind another holder (the common, uncontended case this
+    /// API targets), it already holds the lock, and this releases it right away. Otherwise,
+    /// this queue has no way to identify and skip a queued waiter's predecessor safely, so the
+    /// only sound way to abandon an already-queued permit is still to wait for its turn (same as
+    /// `wait`) and then release right away, rather than leaving the queue in an inconsistent
+    /// state for whoever is behind it.
+    pub fn cancel(mut self) {
+        drop(self.wait_for_turn());
+    }
+
+    /// Does the actual work behind `wait`/`cancel`/`Drop`: spins until this permit's `locked`
+    /// flag clears, then hands back a `Guard` for the slot, exactly once.
+    ///
+    /// Returns `None` if called again after the permit was already resolved (by an earlier call
+    /// from `wait`, `cancel`, or `Drop`), so every caller can resolve unconditionally without
+    /// double-waiting or double-dequeueing.
+    fn wait_for_turn(&mut self) -> Option<Guard<'a, T>> {
+        let slot = self.slot.take()?;
+
+        // Only register as a waiter (for `Mutex::waiters`) if this permit actually has to wait;
+        // an uncontended permit was never really "waiting", matching `Mutex::lock`'s own handling.
+        #[cfg(feature = "diagnostics")]
+        let _waiter_guard = if self.locked.load(Ordering::Relaxed) {
+            self.lock.register_waiter(slot)
+        } else {
+            None
+        };
+        while self.locked.load(Ordering::Relaxed) {
+            #[cfg(feature = "stats")]
+            self.lock.stat_spins.fetch_add(1, Ordering::Relaxed);
+            pause();
+        }
+        fence(Ordering::Acquire);
+
+        #[cfg(feature = "lockdep")]
+        self.lock.lockdep_on_acquire();
+        #[cfg(feature = "prefetch")]
+        prefetch_read(self.lock.data.get());
+        #[cfg(feature = "backtrace")]
+        self.lock.record_acquire_backtrace();
+        #[cfg(feature = "stats")]
+        self.lock.stat_acquisitions.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "fair")]
+        self.lock.record_acquisition_for_fairness();
+        #[cfg(all(feature = "reentrancy-check", debug_assertions))]
+        {
+            *self.lock.reentrancy_owner.lock().unwrap() = Some(thread::current().id());
+        }
+        #[cfg(feature = "watchdog")]
+        {
+            let this_thread = thread::current();
+            let name = this_thread.name().map(ToString::to_string);
+            *self.lock.held_since.lock().unwrap() = Some((Instant::now(), this_thread.id(), name));
+        }
+
+        Some(Guard {
+            lock: self.lock,
+            slot: slot,
+            _not_send: PhantomData
+        })
+    }
+}
+
+/// Dropping a `Permit` without calling `wait` or `cancel` still has to resolve it: this queue
+/// has no way to splice an already-linked `Slot` back out, so a bare drop completes the same
+/// wait `cancel` would and immediately releases the resulting `Guard`. Skipping this would
+/// either use-after-free the heap-allocated `locked` flag (the predecessor writes through a
+/// pointer into it once it unlocks) or wedge the mutex forever (the `Slot` stays linked in
+/// `queue` with nothing to ever signal it).
+#[cfg(feature = "permit")]
+impl<'a, T: ?Sized> Drop for Permit<'a, T> {
+    fn drop(&mut self) {
+        drop(self.wait_for_turn());
+    }
+}
+
+impl<'a, T: ?Sized> Guard<'a, T> {
+    /// Reconstructs a guard from its constituent parts without acquiring the lock.
+    ///
+    /// Used internally to hand a lock's release back to safe `Drop`-based code after it was
+    /// acquired and forgotten through a raw or FFI path.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that `lock` is currently held on behalf of `slot` (i.e. that a
+    /// guard for this exact acquisition was previously forgotten rather than dropped), and that
+    /// at most one guard is reconstructed per acquisition.
+    pub(crate) unsafe fn from_raw_parts(lock: &'a Mutex<T>, slot: &'a Slot) -> Guard<'a, T> {
+        Guard {
+            lock: lock,
+            slot: slot,
+            _not_send: PhantomData
+        }
+    }
+
+    /// Converts a guard into an opaque, raw token without releasing the lock.
+    ///
+    /// This lets the lock be held across a boundary that can't carry a borrowing `Guard`, such
+    /// as a manually managed FFI call or continuation, with release deferred to a later call to
+    /// `Guard::from_raw`. The lock stays held until then.
+    #[cfg(feature = "raw-token")]
+    pub fn into_raw(guard: Guard<'a, T>) -> RawLockToken<'a, T> {
+        let token = RawLockToken {
+            lock: guard.lock,
+            slot: guard.slot,
+            _marker: PhantomData
+        };
+        mem::forget(guard);
+        token
+    }
+
+    /// Reconstructs a guard from a token previously produced by `Guard::into_raw`, resuming
+    /// normal `Drop`-based release.
+    ///
+    /// # Safety
+    ///
+    /// `token` must have been produced by `Guard::into_raw` and not already turned back into a
+    /// guard; doing so twice would release the lock twice.
+    #[cfg(feature = "raw-token")]
+    pub unsafe fn from_raw(token: RawLockToken<'a, T>) -> Guard<'a, T> {
+        Guard::from_raw_parts(token.lock, token.slot)
+    }
+}
+
+/// An opaque token representing a held lock, produced by `Guard::into_raw`.
+///
+/// Captures the mutex and slot references outside of a `Guard`'s `Drop`-based release so the
+/// lock can be handed off to code that can't hold a borrow across the boundary it crosses, and
+/// released later via `Guard::from_raw`.
+#[cfg(feature = "raw-token")]
+pub struct RawLockToken<'a, T: ?Sized + 'a> {
+    lock: &'a Mutex<T>,
+    slot: &'a Slot,
+    _marker: PhantomData<&'a ()>
+}
+
+impl<'a, T: ?Sized> Deref for Guard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for Guard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Borrow<T> for Guard<'a, T> {
+    fn borrow(&self) -> &T {
+        &**self
+    }
+}
+
+impl<'a, T: ?Sized> BorrowMut<T> for Guard<'a, T> {
+    fn borrow_mut(&mut self) -> &mut T {
+        &mut **self
+    }
+}
+
+impl<'a, T: ?Sized + Index<I>, I> Index<I> for Guard<'a, T> {
+    type Output = T::Output;
+    fn index(&self, index: I) -> &T::Output {
+        Index::index(&**self, index)
+    }
+}
+
+impl<'a, T: ?Sized + IndexMut<I>, I> IndexMut<I> for Guard<'a, T> {
+    fn index_mut(&mut self, index: I) -> &mut T::Output {
+        IndexMut::index_mut(&mut **self, index)
+    }
+}
+
+// It has been suggested that a critical section known ahead of time to be read-only could unlock
+// with a weaker ordering than `Release` (e.g. `Relaxed`) on the theory that there's nothing new to
+// publish to the next holder. This has been considered and rejected: the `Release` here isn't
+// only about publishing this critical section's writes, it's what prevents this critical
+// section's operations (loads *and* stores) from being reordered, by the compiler or the
+// hardware, to *after* the store that announces the lock is free. Weaken it and the next holder's
+// `Acquire` can observe the unlock before this thread's reads have actually completed, letting the
+// two critical sections' memory accesses overlap in time even though `Guard`s never coexist,
+// which is a data race on the protected data regardless of whether this section wrote to it. So
+// there is no read-only fast-unlock variant here; every release uses full `Release` ordering.
+//
+// It has also been proposed that the `fence(Acquire)` just before `succ.store(false, Release)`
+// below is redundant for the common single-producer/single-consumer handoff (one waiter, known
+// ahead of time) and could be dropped to save a barrier on architectures like aarch64 where it
+// isn't free. This has been considered and rejected too, though the reasoning is subtler than the
+// read-only case above.
+//
+// What that fence actually does: `succ` was published by the waiter via `pred.next.store(ptr,
+// Release)` (see `lock`); this thread's load of `slot.next` that produced `succ` is `Relaxed`, so
+// on its own it does not synchronize with that store. The `fence(Acquire)` is what turns that
+// `Relaxed` load into the acquire half of the pair, giving this thread a happens-before edge from
+// "the waiter registered itself" to "this thread dereferences `succ` and stores through it".
+//
+// On every real CPU, the store through `succ` right after already carries an address dependency
+// on the value just loaded, and no mainstream architecture (including aarch64) reorders a store
+// ahead of the load that computed its own address. So in practice this fence compiles to nothing
+// observable, dependency ordering alone would be enough. But Rust's atomic memory model (like
+// C++'s) does not recognize dependency ordering as a valid substitute for `Acquire` - the closest
+// equivalent, `memory_order_consume`, was never soundly implementable and both languages
+// deprecated it. Removing the fence would mean relying on real hardware's behavior rather than
+// anything the abstract machine guarantees, which a sufficiently aggressive future compiler
+// optimization (e.g. speculating the store and only committing it once the dependency resolves,
+// then hoisting other reordering around that speculation) would be permitted to break even though
+// no current compiler does. So the fence stays; this is a "not provably safe to remove" rejection,
+// not a "measured and found not worth it" one.
+//
+// Properly settling this either way would mean modeling the exact `AtomicPtr`/`AtomicBool`
+// operations this handoff uses under `loom`, which requires routing every atomic op in `lock`,
+// `try_lock`, and this `Drop` impl through `loom::sync::atomic` behind a `#[cfg(loom)]` shim - a
+// structural change to the crate's hottest path that deserves its own change, not a drive-by
+// tweak bundled with this analysis.
+//
+// This also means loom models for `Permit::cancel`'s abandon-vs-handoff race aren't buildable
+// yet, on top of the `#[cfg(loom)]` shim being missing: `cancel` doesn't actually splice a queued
+// waiter out of the chain early (see its doc comment - this queue doesn't track predecessors, so
+// it still waits for its turn like `wait` does), so there is no genuine early-abandonment code
+// path to model in the first place. A loom model here would have to wait for both the shim above
+// and a real predecessor-tracking (or generation-tagged) cancellable-slot design.
+//
+// Unforturnately, since just putting attributes on generic parameters is unstable, we have to duplicate the whole Drop impl
+#[cfg(feature = "unstable")]
+unsafe impl<'a, #[may_dangle] T: ?Sized> Drop for Guard<'a, T> {
+    fn drop(&mut self) {
+        #[cfg(feature = "lockdep")]
+        self.lock.lockdep_on_release();
+        #[cfg(all(feature = "reentrancy-check", debug_assertions))]
+        {
+            *self.lock.reentrancy_owner.lock().unwrap() = None;
+        }
+        #[cfg(feature = "watchdog")]
+        {
+            *self.lock.held_since.lock().unwrap() = None;
+        }
+        #[cfg(feature = "versioned")]
+        self.lock.version.fetch_add(1, Ordering::Release);
+
+        let mut succ = self.slot.next.load(Ordering::Relaxed);
+        if succ.is_null() {
+            // No one has registered as waiting.
+            if self.lock.queue.compare_exchange(self.slot as *const _ as *mut _, ptr::null_mut(), Ordering::Release, Ordering::Relaxed).is_ok() {
+                // No one was waiting.
+                return;
+            }
+
+            // Some thread is waiting, but hasn't registered yet. Spin waiting for them to register themselves.
+            loop {
+                succ = self.slot.next.load(Ordering::Relaxed);
+                if !succ.is_null() {
+                    break;
+                }
+                pause();
+            }
+        }
+
+        // Announce to the next waiter that the lock is free.
+        fence(Ordering::Acquire);
+        let succ = unsafe { &*succ };
+        succ.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(not(feature = "unstable"))]
+impl<'a, T: ?Sized> Drop for Guard<'a, T> {
+    fn drop(&mut self) {
+        #[cfg(feature = "lockdep")]
+        self.lock.lockdep_on_release();
+        #[cfg(all(feature = "reentrancy-check", debug_assertions))]
+        {
+            *self.lock.reentrancy_owner.lock().unwrap() = None;
+        }
+        #[cfg(feature = "watchdog")]
+        {
+            *self.lock.held_since.lock().unwrap() = None;
+        }
+        #[cfg(feature = "versioned")]
+        self.lock.version.fetch_add(1, Ordering::Release);
+
+        let mut succ = self.slot.next.load(Ordering::Relaxed);
+        if succ.is_null() {
+            // No one has registered as waiting.
+            if self.lock.queue.compare_exchange(self.slot as *const _ as *mut _, ptr::null_mut(), Ordering::Release, Ordering::Relaxed).is_ok() {
+                // No one was waiting.
+                return;
+            }
+
+            // Some thread is waiting, but hasn't registered yet. Spin waiting for them to register themselves.
+            loop {
+                succ = self.slot.next.load(Ordering::Relaxed);
+                if !succ.is_null() {
+                    break;
+                }
+                pause();
+            }
+        }
+
+        // Announce to the next waiter that the lock is free.
+        fence(Ordering::Acquire);
+        let succ = unsafe { &*succ };
+        succ.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Mutex, Slot};
+    #[cfg(feature = "raw-token")]
+    use super::Guard;
+
+    // Mostly stoled from the Rust standard Mutex implementation's tests, so
+
+    // Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+    // file at http://rust-lang.org/COPYRIGHT.
+    //
+    // Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+    // http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+    // <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+    // option. This file may not be copied, modified, or distributed
+    // except according to those terms.
+
+    use std::sync::Arc;
+    use std::sync::mpsc::channel;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    #[derive(Eq, PartialEq, Debug)]
+    struct NonCopy(i32);
+
+    #[test]
+    fn smoke() {
+        let mut slot = Slot::new();
+        let m = Mutex::new(());
+        drop(m.lock(&mut slot));
+        drop(m.lock(&mut slot));
+    }
+
+    #[cfg(feature = "permit")]
+    #[test]
+    fn test_enqueue_cancel_one_permit_then_wait_on_the_other() {
+        let m = Mutex::new(0);
+        let mut slot_a = Slot::new();
+        let mut slot_b = Slot::new();
+
+        let permit_a = m.enqueue(&mut slot_a);
+        let permit_b = m.enqueue(&mut slot_b);
+
+        // `permit_a` was uncontended (nothing held the lock yet), so cancelling it returns
+        // immediately without ever exposing the critical section, and hands off to `permit_b`.
+        permit_a.cancel();
+
+        let mut guard = permit_b.wait();
+        *guard += 1;
+        drop(guard);
+
+        assert_eq!(*m.lock(&mut Slot::new()), 1);
+    }
+
+    #[test]
+    fn test_from_fn_array_seeds_each_mutex_distinctly() {
+        let mutexes: [Mutex<u32>; 4] = Mutex::from_fn_array(|index| index as u32);
+
+        for (index, m) in mutexes.iter().enumerate() {
+            let mut slot = Slot::new();
+            assert_eq!(*m.lock(&mut slot), index as u32);
+        }
+    }
+
+    #[test]
+    fn lots_and_lots() {
+        lazy_static! {
+            static ref LOCK: Mutex<u32> = Mutex::new(0);
+        }
+
+        const ITERS: u32 = 1000;
+        const CONCURRENCY: u32 = 3;
+
+        fn inc() {
+            let mut slot = Slot::new();
+            for _ in 0..ITERS {
+                let mut g = LOCK.lock(&mut slot);
+                *g += 1;
+            }
+        };
+
+        let (tx, rx) = channel();
+        for _ in 0..CONCURRENCY {
+            let tx2 = tx.clone();
+            thread::spawn(move|| { inc(); tx2.send(()).unwrap(); });
+            let tx2 = tx.clone();
+            thread::spawn(move|| { inc(); tx2.send(()).unwrap(); });
+        }
+
+        drop(tx);
+        for _ in 0..2 * CONCURRENCY {
+            rx.recv().unwrap();
+        }
+        let mut slot = Slot::new();
+        assert_eq!(*LOCK.lock(&mut slot), ITERS * CONCURRENCY * 2);
+    }
+
+    #[test]
+    fn test_release_chain_no_orphaned_waiters() {
+        // Hammers the queue with more concurrent waiters than `lots_and_lots` to make sure the
+        // release path (CAS-to-null racing against a not-yet-registered successor) never
+        // orphans a waiter: every increment performed under the lock must be observed.
+        lazy_static! {
+            static ref LOCK: Mutex<u64> = Mutex::new(0);
+        }
+
+        const ITERS: u64 = 2000;
+        const CONCURRENCY: u64 = 8;
+
+        fn inc() {
+            let mut slot = Slot::new();
+            for _ in 0..ITERS {
+                let mut g = LOCK.lock(&mut slot);
+                *g += 1;
+            }
+        }
+
+        let (tx, rx) = channel();
+        for _ in 0..CONCURRENCY {
+            let tx = tx.clone();
+            thread::spawn(move || { inc(); tx.send(()).unwrap(); });
+        }
+
+        drop(tx);
+        for _ in 0..CONCURRENCY {
+            rx.recv().unwrap();
+        }
+        let mut slot = Slot::new();
+        assert_eq!(*LOCK.lock(&mut slot), ITERS * CONCURRENCY);
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn test_waiters_dump_includes_label() {
+        use std::sync::Barrier;
+
+        lazy_static! {
+            static ref LOCK: Mutex<()> = Mutex::new(());
+        }
+
+        let mut holder_slot = Slot::new();
+        let guard = LOCK.lock(&mut holder_slot);
+
+        let barrier = Arc::new(Barrier::new(2));
+        let barrier2 = barrier.clone();
+        let t = thread::spawn(move || {
+            let mut slot = Slot::labeled("render-thread");
+            barrier2.wait();
+            drop(LOCK.lock(&mut slot));
+        });
+
+        barrier.wait();
+        // Give the waiter thread a moment to register itself in the queue.
+        while LOCK.waiters().is_empty() {
+            thread::yield_now();
+        }
+        assert_eq!(LOCK.waiters(), ["render-thread"]);
+
+        drop(guard);
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn test_guard_index() {
+        let mut slot = Slot::new();
+        let m = Mutex::new([1, 2, 3, 4]);
+        {
+            let mut g = m.lock(&mut slot);
+            g[0] = 10;
+        }
+        let g = m.lock(&mut slot);
+        assert_eq!(g[0], 10);
+        assert_eq!(g[3], 4);
+    }
+
+    #[test]
+    fn test_replace_if() {
+        let mut slot = Slot::new();
+        let m = Mutex::new(10);
+
+        assert_eq!(m.replace_if(&mut slot, |&v| v > 100, || 0), None);
+        assert_eq!(*m.lock(&mut slot), 10);
+
+        assert_eq!(m.replace_if(&mut slot, |&v| v == 10, || 20), Some(10));
+        assert_eq!(*m.lock(&mut slot), 20);
+    }
+
+    #[cfg(feature = "raw-token")]
+    #[test]
+    fn test_into_raw_from_raw_round_trip() {
+        let mut slot = Slot::new();
+        let m = Mutex::new(0);
+
+        let guard = m.lock(&mut slot);
+        let token = Guard::into_raw(guard);
+
+        // The lock is still held: another attempt must fail.
+        let mut other_slot = Slot::new();
+        assert!(m.try_lock(&mut other_slot).is_err());
+
+        drop(unsafe { Guard::from_raw(token) });
+
+        // Released: a subsequent lock now succeeds.
+        assert!(m.try_lock(&mut other_slot).is_ok());
+    }
+
+    #[cfg(feature = "raw-token")]
+    #[test]
+    fn test_raw_try_lock_then_raw_unlock_round_trip() {
+        let mut slot = Slot::new();
+        let m = Mutex::new(0u32);
+
+        assert!(m.raw_try_lock(&mut slot));
+
+        // The lock is held: another acquisition attempt must fail.
+        let mut other_slot = Slot::new();
+        assert!(!m.raw_try_lock(&mut other_slot));
+
+        unsafe {
+            *m.data_ptr() += 1;
+            m.raw_unlock(&slot);
+        }
+
+        // Released: a subsequent acquisition now succeeds.
+        assert!(m.raw_try_lock(&mut other_slot));
+        unsafe { m.raw_unlock(&other_slot); }
+        assert_eq!(unsafe { *m.get_mut_unchecked() }, 1);
+    }
+
+    #[cfg(all(feature = "raw-token", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "raw_unlock called without a matching successful raw_try_lock")]
+    fn test_raw_unlock_without_matching_raw_try_lock_panics_in_debug() {
+        let slot = Slot::new();
+        let m = Mutex::new(0u32);
+
+        unsafe { m.raw_unlock(&slot); }
+    }
+
+    #[test]
+    fn test_try_lock_explicit_default_orderings() {
+        let mut slot = Slot::new();
+        let m = Mutex::new(0);
+        {
+            let mut g = m.try_lock_explicit(&mut slot, Ordering::Acquire, Ordering::Relaxed).unwrap();
+            *g += 1;
+        }
+        assert_eq!(*m.lock(&mut slot), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "failure ordering must not be Release or AcqRel")]
+    #[cfg(debug_assertions)]
+    fn test_try_lock_explicit_rejects_invalid_failure_ordering() {
+        let mut slot = Slot::new();
+        let m = Mutex::new(0);
+        let _ = m.try_lock_explicit(&mut slot, Ordering::AcqRel, Ordering::Release);
+    }
+
+    #[test]
+    fn test_get_mut_unchecked() {
+        let m = Mutex::new(NonCopy(1));
+        unsafe {
+            *m.get_mut_unchecked() = NonCopy(2);
+        }
+        assert_eq!(m.into_inner(), NonCopy(2));
+    }
+
+    #[test]
+    fn test_try_get_mut() {
+        let mut m = Mutex::new(1);
+        assert_eq!(m.try_get_mut(), Some(&mut 1));
+        *m.try_get_mut().unwrap() = 2;
+        assert_eq!(m.into_inner(), 2);
+    }
+
+    #[test]
+    fn try_lock() {
+        let mut slot = Slot::new();
+        let m = Mutex::new(());
+        *m.try_lock(&mut slot).unwrap() = ();
+    }
+
+    #[test]
+    fn test_into_inner() {
+        let m = Mutex::new(NonCopy(10));
+        assert_eq!(m.into_inner(), NonCopy(10));
+    }
+
+    #[test]
+    fn test_into_inner_drop() {
+        struct Foo(Arc<AtomicUsize>);
         impl Drop for Foo {
             fn drop(&mut self) {
                 self.0.fetch_add(1, Ordering::SeqCst);
@@ -388,6 +1909,153 @@ mod test {
         assert_eq!(*lock, 2);
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_lock_timed_warns_on_slow_section() {
+        use std::sync::atomic::AtomicBool;
+        use std::time::Duration;
+
+        let mut slot = Slot::new();
+        let m = Mutex::new(0);
+        let warned = AtomicBool::new(false);
+
+        m.lock_timed(&mut slot, Duration::from_millis(10), |data| {
+            *data += 1;
+            thread::sleep(Duration::from_millis(50));
+        }, |_elapsed| {
+            warned.store(true, Ordering::SeqCst);
+        });
+
+        assert!(warned.load(Ordering::SeqCst));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_lock_measured_reports_hold_time_unconditionally() {
+        use std::sync::mpsc::channel;
+        use std::time::Duration;
+
+        let mut slot = Slot::new();
+        let m = Mutex::new(0);
+        let (tx, rx) = channel();
+
+        {
+            let mut guard = m.lock_measured(&mut slot, move |elapsed| {
+                tx.send(elapsed).unwrap();
+            });
+            *guard += 1;
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        let elapsed = rx.recv().expect("sink should have fired on drop");
+        assert!(elapsed >= Duration::from_millis(20));
+        assert_eq!(*m.lock(&mut slot), 1);
+    }
+
+    #[cfg(feature = "versioned")]
+    #[test]
+    fn test_lock_if_version_rejects_a_stale_version_and_accepts_a_matching_one() {
+        let mut slot = Slot::new();
+        let m = Mutex::new(0);
+
+        let seen_version = m.version();
+
+        // Someone else touches the data before we get a chance to act on `seen_version`.
+        *m.lock(&mut slot) += 1;
+
+        assert!(m.lock_if_version(&mut slot, seen_version).is_none());
+
+        let fresh_version = m.version();
+        assert!(m.lock_if_version(&mut slot, fresh_version).is_some());
+    }
+
+    // `lock_no_unwind`'s whole point is to abort the process instead of unwinding when `f`
+    // panics, which by design can't be observed from within the same test process via
+    // `catch_unwind` or `#[should_panic]` (there is no unwind to catch, and no panic message to
+    // match - the process is gone). Its non-panicking path is exercised here; the abort path is
+    // covered by this method's doc comment instead of an automated test.
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_lock_no_unwind_returns_normally_when_f_does_not_panic() {
+        let mut slot = Slot::new();
+        let m = Mutex::new(0);
+
+        let doubled = m.lock_no_unwind(&mut slot, |data| {
+            *data += 1;
+            *data * 2
+        });
+
+        assert_eq!(doubled, 2);
+        assert_eq!(*m.lock(&mut slot), 1);
+    }
+
+    #[cfg(feature = "lazy-init")]
+    #[test]
+    fn test_write_init_then_assume_init_ref_reads_the_value() {
+        use core::mem::MaybeUninit;
+
+        let m: Mutex<MaybeUninit<u32>> = Mutex::new(MaybeUninit::uninit());
+        let mut slot = Slot::new();
+
+        m.write_init(&mut slot, 42);
+        let guard = unsafe { m.assume_init_ref(&mut slot) };
+        assert_eq!(*guard, 42);
+    }
+
+    #[cfg(all(feature = "lazy-init", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "assume_init_ref called before write_init")]
+    fn test_assume_init_ref_before_write_init_panics_in_debug() {
+        use core::mem::MaybeUninit;
+
+        let m: Mutex<MaybeUninit<u32>> = Mutex::new(MaybeUninit::uninit());
+        let mut slot = Slot::new();
+
+        unsafe { m.assume_init_ref(&mut slot) };
+    }
+
+    #[cfg(feature = "release-hook")]
+    #[test]
+    fn test_release_hook_fires_once_after_dequeue_completes() {
+        use std::sync::atomic::AtomicUsize;
+
+        let lock = Mutex::new(0u32);
+        let mut slot = Slot::new();
+        let hook_calls = AtomicUsize::new(0);
+
+        {
+            let mut guard = lock.lock_with_release_hook(&mut slot, |released_slot: &Slot| {
+                hook_calls.fetch_add(1, Ordering::SeqCst);
+                // No other thread ever queued behind this slot, so it has no successor linked
+                // by the time the hook runs.
+                assert!(released_slot.next.load(Ordering::Relaxed).is_null());
+            });
+            *guard += 1;
+        }
+
+        assert_eq!(hook_calls.load(Ordering::SeqCst), 1);
+        assert!(lock.queue.load(Ordering::Relaxed).is_null());
+        assert_eq!(*lock.lock(&mut slot), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_extract_arc_with_multiple_owners() {
+        let arc = Arc::new(Mutex::new(41));
+        let _arc2 = arc.clone();
+        let _arc3 = arc.clone();
+
+        let mut slot = Slot::new();
+        {
+            let mut guard = arc.lock(&mut slot);
+            *guard += 1;
+        }
+
+        let mut slot = Slot::new();
+        assert_eq!(Mutex::extract_arc(&arc, &mut slot), 42);
+        assert_eq!(Arc::strong_count(&arc), 3);
+    }
+
     #[test]
     fn test_lock_unsized() {
         let mut slot = Slot::new();
@@ -400,4 +2068,490 @@ mod test {
         let comp: &[i32] = &[4, 2, 5];
         assert_eq!(&*lock.lock(&mut slot), comp);
     }
+
+    // Two mutexes tagged with two classes, acquired in one order and then in the opposite order
+    // on the same thread, should be flagged as an ABBA lock-ordering violation even though this
+    // run can't actually deadlock (the first pair is fully released before the second starts).
+    #[cfg(feature = "lockdep")]
+    #[test]
+    #[should_panic(expected = "lockdep")]
+    fn test_lockdep_flags_inconsistent_ordering() {
+        let a = Mutex::new(());
+        let b = Mutex::new(());
+        a.set_lock_class("lockdep-test-a");
+        b.set_lock_class("lockdep-test-b");
+
+        let mut slot_a = Slot::new();
+        let mut slot_b = Slot::new();
+        {
+            let _guard_a = a.lock(&mut slot_a);
+            let _guard_b = b.lock(&mut slot_b);
+        }
+
+        // Opposite order: `b` before `a`. This is inconsistent with the ordering just recorded
+        // above, so it should panic on the second acquisition.
+        let _guard_b = b.lock(&mut slot_b);
+        let _guard_a = a.lock(&mut slot_a);
+    }
+
+    #[cfg(feature = "unstable")]
+    #[test]
+    fn test_slot_default_const_array() {
+        let slots = [Slot::DEFAULT; 8];
+        assert_eq!(slots.len(), 8);
+    }
+
+    #[test]
+    fn test_slot_implements_default() {
+        #[derive(Default)]
+        struct WithSlot {
+            #[allow(dead_code)]
+            slot: Slot
+        }
+
+        let _with_slot: WithSlot = Default::default();
+    }
+
+    #[test]
+    fn test_lock_or_retry_breaks_out_once_free() {
+        use std::ops::ControlFlow;
+        use std::time::Duration;
+
+        lazy_static! {
+            static ref LOCK: Mutex<i32> = Mutex::new(0);
+        }
+
+        let mut holder_slot = Slot::new();
+        let guard = LOCK.lock(&mut holder_slot);
+
+        let retrier = thread::spawn(|| {
+            let mut slot = Slot::new();
+            loop {
+                match LOCK.lock_or_retry(&mut slot) {
+                    ControlFlow::Break(guard) => break *guard,
+                    ControlFlow::Continue(()) => continue
+                }
+            }
+        });
+
+        thread::sleep(Duration::from_millis(20));
+        drop(guard);
+
+        let acquired = retrier.join().unwrap();
+        assert_eq!(acquired, 0);
+    }
+
+    #[cfg(feature = "allocator-api")]
+    #[test]
+    fn test_new_in_uses_the_given_allocator() {
+        use std::alloc::{AllocError, Allocator, Global, Layout};
+        use std::ptr::NonNull;
+
+        struct CountingAlloc<'a> {
+            count: &'a AtomicUsize
+        }
+
+        unsafe impl<'a> Allocator for CountingAlloc<'a> {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                self.count.fetch_add(1, Ordering::SeqCst);
+                Global.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                Global.deallocate(ptr, layout)
+            }
+        }
+
+        let count = AtomicUsize::new(0);
+        let boxed = Mutex::new_in(5, CountingAlloc { count: &count });
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        let mut slot = Slot::new();
+        assert_eq!(*boxed.lock(&mut slot), 5);
+    }
+
+    #[test]
+    fn test_guard_borrow_mut() {
+        use std::borrow::BorrowMut;
+
+        fn take(x: impl BorrowMut<i32>) -> i32 {
+            let mut x = x;
+            *x.borrow_mut() += 1;
+            *x.borrow_mut()
+        }
+
+        let mut slot = Slot::new();
+        let lock = Mutex::new(41);
+        let guard = lock.lock(&mut slot);
+        assert_eq!(take(guard), 42);
+    }
+
+    #[cfg(feature = "backtrace")]
+    #[test]
+    fn test_last_acquire_backtrace_is_populated() {
+        let mut slot = Slot::new();
+        let lock = Mutex::new(0);
+
+        assert!(lock.last_acquire_backtrace().is_none());
+
+        drop(lock.lock(&mut slot));
+
+        let backtrace = lock.last_acquire_backtrace().expect("backtrace should have been recorded");
+        assert!(!format!("{:?}", backtrace).is_empty());
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn test_stats_counts_acquisitions_and_contention() {
+        let lock = Mutex::new(0);
+
+        let mut slot = Slot::new();
+        drop(lock.try_lock(&mut slot).unwrap());
+
+        let stats = lock.stats();
+        assert_eq!(stats.acquisitions, 1);
+        assert_eq!(stats.contended_acquisitions, 0);
+
+        // Hold the lock on one thread while another blocks in `lock`, to force a contended,
+        // spinning acquisition that the uncontended `try_lock` above can't exercise.
+        use std::sync::Arc;
+        use std::thread;
+
+        let lock = Arc::new(lock);
+        let held_slot = Slot::new();
+        let mut holder_slot = held_slot;
+        let guard = lock.lock(&mut holder_slot);
+
+        let lock2 = lock.clone();
+        let waiter = thread::spawn(move || {
+            let mut slot = Slot::new();
+            drop(lock2.lock(&mut slot));
+        });
+
+        thread::sleep(::std::time::Duration::from_millis(50));
+        drop(guard);
+        waiter.join().unwrap();
+
+        let stats = lock.stats();
+        assert_eq!(stats.acquisitions, 3);
+        assert_eq!(stats.contended_acquisitions, 1);
+        assert!(stats.total_spins > 0);
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn test_fast_path_ratio_moves_between_uncontended_and_contended_regimes() {
+        let lock = Mutex::new(0);
+        assert_eq!(lock.fast_path_ratio(), 0.0);
+
+        // Uncontended: every call should hit the fast path.
+        for _ in 0..10 {
+            drop(lock.lock_fast_path(&mut Slot::new()));
+        }
+        assert_eq!(lock.fast_path_ratio(), 1.0);
+
+        // Contended: hold the lock on one thread while another calls `lock_fast_path`, forcing it
+        // to miss the CAS and fall back to the queue.
+        use std::sync::Arc;
+        use std::thread;
+
+        let lock = Arc::new(lock);
+        let mut slot = Slot::new();
+        let guard = lock.lock(&mut slot);
+
+        let lock2 = lock.clone();
+        let waiter = thread::spawn(move || {
+            drop(lock2.lock_fast_path(&mut Slot::new()));
+        });
+
+        thread::sleep(::std::time::Duration::from_millis(50));
+        drop(guard);
+        waiter.join().unwrap();
+
+        assert!(lock.fast_path_ratio() < 1.0);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_export_metrics_contains_expected_lines_after_contention() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let lock = Arc::new(Mutex::new(0));
+
+        let mut holder_slot = Slot::new();
+        let guard = lock.lock(&mut holder_slot);
+
+        let lock2 = lock.clone();
+        let waiter = thread::spawn(move || {
+            let mut slot = Slot::new();
+            drop(lock2.lock(&mut slot));
+        });
+
+        thread::sleep(::std::time::Duration::from_millis(50));
+        drop(guard);
+        waiter.join().unwrap();
+
+        let text = lock.export_metrics("mymutex");
+
+        assert!(text.contains("# HELP mymutex_acquisitions_total"));
+        assert!(text.contains("# TYPE mymutex_acquisitions_total counter"));
+        assert!(text.contains("mymutex_acquisitions_total 2\n"));
+        assert!(text.contains("# HELP mymutex_contended_acquisitions_total"));
+        assert!(text.contains("mymutex_contended_acquisitions_total 1\n"));
+        assert!(text.contains("# HELP mymutex_spins_total"));
+        assert!(text.contains("# TYPE mymutex_spins_total counter"));
+    }
+
+    #[cfg(feature = "fair")]
+    #[test]
+    fn test_max_consecutive_same_thread_bounds_starvation() {
+        use std::sync::atomic::AtomicBool;
+        use std::sync::Arc;
+        use std::thread;
+
+        const MAX_STREAK: usize = 5;
+
+        let lock = Arc::new(Mutex::new(0u32));
+        lock.set_max_consecutive_same_thread(MAX_STREAK);
+
+        let done = Arc::new(AtomicBool::new(false));
+        let occasional_acquired = Arc::new(AtomicBool::new(false));
+
+        let lock2 = lock.clone();
+        let done2 = done.clone();
+        let relocker = thread::spawn(move || {
+            let mut slot = Slot::new();
+            while !done2.load(Ordering::Relaxed) {
+                *lock2.lock(&mut slot) += 1;
+            }
+        });
+
+        let lock3 = lock.clone();
+        let occasional_acquired2 = occasional_acquired.clone();
+        let occasional = thread::spawn(move || {
+            let mut slot = Slot::new();
+            lock3.lock(&mut slot);
+            occasional_acquired2.store(true, Ordering::Relaxed);
+        });
+
+        occasional.join().unwrap();
+        assert!(occasional_acquired.load(Ordering::Relaxed), "occasional acquirer was starved");
+
+        done.store(true, Ordering::Relaxed);
+        relocker.join().unwrap();
+    }
+
+    // The measurement harness for the `fair` feature's documented fairness guarantee (see
+    // `set_max_consecutive_same_thread`'s doc comment): runs several threads hammering the same
+    // mutex, has each acquisition record whether it continues or breaks the previous acquirer's
+    // streak, and asserts the longest same-thread streak actually observed stays within a
+    // generous multiple of the configured bound.
+    #[cfg(feature = "fair")]
+    #[test]
+    fn test_fairness_bypass_bound_measured_under_heavy_contention() {
+        use std::sync::Arc;
+        use std::sync::atomic::AtomicBool;
+        use std::thread;
+        use std::time::Duration;
+        use std::vec::Vec;
+
+        const MAX_STREAK: usize = 4;
+        const HAMMERS: usize = 4;
+        const RUN_MILLIS: u64 = 200;
+
+        struct State {
+            current_owner: Option<usize>,
+            streak: usize,
+            max_observed_streak: usize
+        }
+
+        let lock = Arc::new(Mutex::new(State { current_owner: None, streak: 0, max_observed_streak: 0 }));
+        lock.set_max_consecutive_same_thread(MAX_STREAK);
+
+        let done = Arc::new(AtomicBool::new(false));
+
+        let mut hammers = Vec::new();
+        for id in 0..HAMMERS {
+            let lock = lock.clone();
+            let done = done.clone();
+            hammers.push(thread::spawn(move || {
+                let mut slot = Slot::new();
+                while !done.load(Ordering::Relaxed) {
+                    let mut guard = lock.lock(&mut slot);
+                    if guard.current_owner == Some(id) {
+                        guard.streak += 1;
+                    } else {
+                        guard.current_owner = Some(id);
+                        guard.streak = 1;
+                    }
+                    if guard.streak > guard.max_observed_streak {
+                        guard.max_observed_streak = guard.streak;
+                    }
+                }
+            }));
+        }
+
+        thread::sleep(Duration::from_millis(RUN_MILLIS));
+        done.store(true, Ordering::Relaxed);
+        for hammer in hammers {
+            hammer.join().unwrap();
+        }
+
+        let mut slot = Slot::new();
+        let max_observed = lock.lock(&mut slot).max_observed_streak;
+
+        // `yield_if_monopolizing` only yields via `thread::yield_now`, a scheduling hint rather
+        // than a hard block, so the same thread can occasionally win the race again right after
+        // yielding. A generous multiple of the configured bound (rather than the bound itself) is
+        // asserted here to catch a genuinely broken bound (e.g. the check not firing at all,
+        // which would let a single hammer run unboundedly for the whole 200ms) without flaking
+        // under heavy scheduler contention.
+        assert!(
+            max_observed <= MAX_STREAK * 8,
+            "observed a same-thread streak of {} acquisitions, far past the configured bound of {}",
+            max_observed, MAX_STREAK
+        );
+    }
+
+    #[cfg(feature = "first-acquire")]
+    #[test]
+    fn test_lock_first_reports_true_exactly_once() {
+        use std::sync::Arc;
+        use std::thread;
+        use std::vec::Vec;
+
+        const THREADS: usize = 16;
+
+        let lock = Arc::new(Mutex::new(0u32));
+        let first_count = Arc::new(AtomicUsize::new(0));
+
+        let mut handles = Vec::new();
+        for _ in 0..THREADS {
+            let lock = lock.clone();
+            let first_count = first_count.clone();
+            handles.push(thread::spawn(move || {
+                let mut slot = Slot::new();
+                let (_guard, first) = lock.lock_first(&mut slot);
+                if first {
+                    first_count.fetch_add(1, Ordering::Relaxed);
+                }
+            }));
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(first_count.load(Ordering::Relaxed), 1);
+    }
+
+    // The prefetch is purely a hint, so correctness must be unaffected by it.
+    #[cfg(feature = "prefetch")]
+    #[test]
+    fn test_prefetch_does_not_affect_correctness() {
+        let mut slot = Slot::new();
+        let lock = Mutex::new(0u64);
+        for i in 0..100 {
+            *lock.lock(&mut slot) = i;
+            assert_eq!(*lock.lock(&mut slot), i);
+        }
+    }
+
+    // Not run as part of normal `cargo test`: this measures wall-clock time rather than asserting
+    // a specific outcome, so it's only useful when run manually (`cargo test --ignored`) to see
+    // the prefetch's effect on a pointer-chasing workload.
+    #[cfg(feature = "prefetch")]
+    #[test]
+    #[ignore]
+    fn bench_lock_then_touch_data() {
+        let mut slot = Slot::new();
+        let lock = Mutex::new([0u8; 4096]);
+
+        let start = std::time::Instant::now();
+        for _ in 0..1_000_000 {
+            let mut guard = lock.lock(&mut slot);
+            guard[0] = guard[0].wrapping_add(1);
+        }
+        let elapsed = start.elapsed();
+
+        println!("1_000_000 lock+touch iterations took {:?} with prefetch enabled", elapsed);
+    }
+
+    // Measures the round-trip cost of the two-thread (single-producer/single-consumer) handoff
+    // path this Drop impl uses, to weigh against the `fence(Acquire)` analysis above: this is
+    // where that fence's cost, if any, would show up on aarch64. `#[ignore]`d since it's a
+    // measurement, not a correctness check, and its result is architecture-dependent.
+    #[cfg(target_arch = "aarch64")]
+    #[ignore]
+    #[test]
+    fn bench_spsc_handoff_round_trip() {
+        use std::sync::Arc;
+        use std::sync::mpsc::sync_channel;
+        use std::thread;
+
+        const ROUND_TRIPS: u32 = 100_000;
+
+        let lock = Arc::new(Mutex::new(0u32));
+        let mut slot_a = Slot::new();
+        // Prime the queue so the "other" thread starts out as the current holder.
+        let guard = lock.lock(&mut slot_a);
+
+        let lock2 = lock.clone();
+        let (ready_tx, ready_rx) = sync_channel(0);
+        let handle = thread::spawn(move || {
+            let mut slot_b = Slot::new();
+            ready_tx.send(()).unwrap();
+            for _ in 0..ROUND_TRIPS {
+                drop(lock2.lock(&mut slot_b));
+            }
+        });
+
+        ready_rx.recv().unwrap();
+        drop(guard);
+
+        let start = std::time::Instant::now();
+        for _ in 0..ROUND_TRIPS {
+            drop(lock.lock(&mut slot_a));
+        }
+        let elapsed = start.elapsed();
+
+        handle.join().unwrap();
+
+        println!("{} SPSC lock handoff round trips took {:?}", ROUND_TRIPS, elapsed);
+    }
+
+    #[cfg(all(feature = "reentrancy-check", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "reentrant lock attempt")]
+    fn test_lock_panics_on_reentrant_acquisition() {
+        let lock = Mutex::new(0u32);
+        let mut slot_a = Slot::new();
+        let mut slot_b = Slot::new();
+
+        let _outer = lock.lock(&mut slot_a);
+        lock.lock(&mut slot_b);
+    }
+
+    #[cfg(all(feature = "reentrancy-check", debug_assertions))]
+    #[test]
+    fn test_reentrancy_check_does_not_affect_cross_thread_locking() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let lock = Arc::new(Mutex::new(0u32));
+
+        let mut slot_a = Slot::new();
+        *lock.lock(&mut slot_a) += 1;
+
+        let lock2 = lock.clone();
+        let handle = thread::spawn(move || {
+            let mut slot_b = Slot::new();
+            *lock2.lock(&mut slot_b) += 1;
+        });
+        handle.join().unwrap();
+
+        let mut slot_c = Slot::new();
+        assert_eq!(*lock.lock(&mut slot_c), 2);
+    }
 }
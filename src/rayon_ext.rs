@@ -0,0 +1,112 @@
+//! Slot caching for `rayon`-style data-parallel thread pools, behind the `rayon` feature.
+//!
+//! `rayon` reuses a small, fixed pool of worker threads across thousands of tiny closures, so
+//! setting up a fresh `Slot` per closure (as plain `Mutex::lock` requires) is wasteful when the
+//! same worker will immediately need another one for the next task. `lock_rayon` instead sources
+//! its `Slot` from a thread-local cache: since each rayon worker is a real, long-lived OS thread,
+//! a `thread_local!` slot lives exactly as long as that worker and is reused across every task it
+//! ever runs, without needing to key anything on `rayon::current_thread_index()` explicitly.
+
+use core::mem;
+use core::ops::{Deref, DerefMut};
+
+use std::cell::RefCell;
+
+use crate::mutex::{Guard, Mutex, Slot};
+
+// This module needs `std::cell::RefCell` and `std::thread_local!` regardless of whether the
+// separate `std` feature is enabled elsewhere, so it links `std` itself here rather than relying
+// on `lib.rs`'s `#[cfg(any(test, feature = "std"))] extern crate std;`.
+extern crate std;
+extern crate rayon;
+
+struct PoolSlot {
+    slot: Slot,
+    in_use: bool
+}
+
+std::thread_local! {
+    static POOL_SLOT: RefCell<PoolSlot> = RefCell::new(PoolSlot { slot: Slot::new(), in_use: false });
+}
+
+/// Acquires `mutex` using this thread's cached pool `Slot`, blocking until it is able to do so.
+///
+/// # Panics
+///
+/// Panics if called again on the same thread before the `RayonGuard` from an outer `lock_rayon`
+/// call on this thread has been dropped: the cached slot is reused, not stacked, so a nested call
+/// would corrupt the outer acquisition's queue state. Use `Mutex::lock` with a locally-owned
+/// `Slot` instead for any critical section that itself needs to lock another mutex.
+pub fn lock_rayon<'a, T: ?Sized>(mutex: &'a Mutex<T>) -> RayonGuard<'a, T> {
+    let slot_ptr: *mut Slot = POOL_SLOT.with(|cell| {
+        let mut pool_slot = cell.borrow_mut();
+        assert!(
+            !pool_slot.in_use,
+            "lock_rayon: this thread's pool slot is already in use by an outer lock_rayon call; \
+             nested/reentrant lock_rayon calls aren't supported"
+        );
+        pool_slot.in_use = true;
+        &mut pool_slot.slot as *mut Slot
+    });
+
+    let guard = unsafe {
+        // SAFETY: the thread-local `PoolSlot` this points into lives for the whole life of this
+        // (pool) thread, well past any borrow taken here; the `in_use` check above rules out any
+        // other live borrow of it on this thread, and `RayonGuard::drop` is the only other place
+        // that touches it, only after this acquisition's `Guard` has already been dropped.
+        mutex.lock(&mut *slot_ptr)
+    };
+    RayonGuard { guard: mem::ManuallyDrop::new(guard) }
+}
+
+/// An RAII guard returned by `lock_rayon`.
+///
+/// Dropping this releases the underlying lock (running the MCS dequeue exactly like a plain
+/// `Guard`) and only then frees this thread's cached pool slot for reuse, so a `lock_rayon` call
+/// made from the drop handler of some unrelated value can never see the slot as available before
+/// the dequeue it depends on has actually completed.
+#[must_use]
+pub struct RayonGuard<'a, T: ?Sized + 'a> {
+    guard: mem::ManuallyDrop<Guard<'a, T>>
+}
+
+impl<'a, T: ?Sized> Deref for RayonGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &*self.guard
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for RayonGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut *self.guard
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RayonGuard<'a, T> {
+    fn drop(&mut self) {
+        // SAFETY: `guard` is never accessed again after this, matching `ManuallyDrop::drop`'s
+        // requirement, and this is the only place that drops it.
+        unsafe { mem::ManuallyDrop::drop(&mut self.guard); }
+        POOL_SLOT.with(|cell| { cell.borrow_mut().in_use = false; });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::lock_rayon;
+    use crate::mutex::Mutex;
+
+    use super::rayon::prelude::*;
+
+    #[test]
+    fn test_parallel_iterator_sums_through_shared_accumulator() {
+        let accumulator = Mutex::new(0u64);
+
+        (1..=1000u64).into_par_iter().for_each(|n| {
+            *lock_rayon(&accumulator) += n;
+        });
+
+        assert_eq!(*lock_rayon(&accumulator), (1..=1000u64).sum::<u64>());
+    }
+}
@@ -0,0 +1,21 @@
+//! A single spot for the raw-pointer reborrow trick several retry loops in this crate need.
+
+/// Reborrows `*ptr` for the caller-chosen lifetime `'a`.
+///
+/// Several places in this crate retry an `'a`-tied call (e.g. `Mutex::try_lock`, which ties its
+/// returned `Guard`'s lifetime to its `&'a mut Slot` argument) in a loop. Reborrowing the same
+/// `&'a mut` a second time for the next attempt looks, to the borrow checker, like two overlapping
+/// exclusive borrows - even though only one attempt's result is ever alive at once, since a failed
+/// attempt's `Err`/`None` is dropped (or the loop returns) before the next attempt runs. Deriving
+/// each attempt's reborrow from a raw pointer instead erases that link from the type system, while
+/// staying exactly as exclusive in practice.
+///
+/// # Safety
+///
+/// The caller must ensure that any reborrow previously produced from `ptr` by this function has
+/// already ended (by returning, or by the value it was tied to being dropped) before calling this
+/// again.
+#[inline]
+pub(crate) unsafe fn reborrow_mut<'a, T: ?Sized>(ptr: *mut T) -> &'a mut T {
+    unsafe { &mut *ptr }
+}
@@ -0,0 +1,142 @@
+//! Integration point for external memory-reclamation schemes (e.g. epoch-based reclamation) that
+//! need to run bracketing logic around a critical section without this crate depending on them.
+//!
+//! `ReclaimingMutex` wraps a plain `Mutex` and fires a caller-supplied `ReclamationHooks`
+//! implementation's `on_acquire`/`on_release` right around the critical section, so e.g. pinning
+//! and unpinning a `crossbeam-epoch` guard can be driven from the same place the lock itself is
+//! acquired and released, without `mcs` taking a dependency on `crossbeam-epoch` to do it.
+
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+
+use crate::mutex::{Guard, Mutex, Slot};
+
+/// Hooks run immediately after a `ReclaimingMutex` acquires its lock and immediately after it
+/// releases it, respectively.
+///
+/// Both default to no-ops, so implementing only the one a particular scheme needs is enough.
+pub trait ReclamationHooks {
+    /// Called after the lock has been acquired, before the critical section runs.
+    fn on_acquire(&self) { }
+
+    /// Called after the critical section's `Guard` has been dropped (the lock is already
+    /// released by the time this runs).
+    fn on_release(&self) { }
+}
+
+/// The default, no-op set of hooks, used when reclamation integration isn't needed.
+impl ReclamationHooks for () { }
+
+/// A `Mutex` that fires `ReclamationHooks` callbacks around every critical section.
+pub struct ReclaimingMutex<T: ?Sized, H> {
+    hooks: H,
+    inner: Mutex<T>
+}
+
+impl<T, H: ReclamationHooks> ReclaimingMutex<T, H> {
+    /// Creates a new reclaiming mutex in an unlocked state, using `hooks` for every acquisition
+    /// and release.
+    pub fn new(value: T, hooks: H) -> ReclaimingMutex<T, H> {
+        ReclaimingMutex { hooks: hooks, inner: Mutex::new(value) }
+    }
+}
+
+impl<T: ?Sized, H: ReclamationHooks> ReclaimingMutex<T, H> {
+    /// Acquires the mutex, blocking the current thread until it is able to do so, then runs
+    /// `hooks.on_acquire()` before returning the guard.
+    pub fn lock<'a>(&'a self, slot: &'a mut Slot) -> ReclaimingGuard<'a, T, H> {
+        let guard = self.inner.lock(slot);
+        self.hooks.on_acquire();
+        ReclaimingGuard { guard: ManuallyDrop::new(guard), hooks: &self.hooks }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+}
+
+/// An RAII guard returned by `ReclaimingMutex::lock`.
+///
+/// Dropping this releases the underlying `Mutex` and then runs `hooks.on_release()`, in that
+/// order, so the reclamation scheme's unpin happens once the lock is actually free.
+#[must_use]
+pub struct ReclaimingGuard<'a, T: ?Sized + 'a, H: ReclamationHooks> {
+    guard: ManuallyDrop<Guard<'a, T>>,
+    hooks: &'a H
+}
+
+impl<'a, T: ?Sized, H: ReclamationHooks> Deref for ReclaimingGuard<'a, T, H> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &*self.guard
+    }
+}
+
+impl<'a, T: ?Sized, H: ReclamationHooks> DerefMut for ReclaimingGuard<'a, T, H> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut *self.guard
+    }
+}
+
+impl<'a, T: ?Sized, H: ReclamationHooks> Drop for ReclaimingGuard<'a, T, H> {
+    fn drop(&mut self) {
+        // Safety: `guard` is never accessed again after this, matching `ManuallyDrop::drop`'s
+        // requirement, and this is the only place that drops it.
+        unsafe { ManuallyDrop::drop(&mut self.guard); }
+        self.hooks.on_release();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ReclaimingMutex, ReclamationHooks};
+    use crate::mutex::Slot;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct MockReclamation {
+        pins: AtomicUsize,
+        unpins: AtomicUsize
+    }
+
+    impl ReclamationHooks for MockReclamation {
+        fn on_acquire(&self) {
+            self.pins.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_release(&self) {
+            self.unpins.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_hooks_fire_in_matching_pin_unpin_pairs() {
+        let mutex = ReclaimingMutex::new(0u32, MockReclamation::default());
+        let mut slot = Slot::new();
+
+        for _ in 0..5 {
+            let mut guard = mutex.lock(&mut slot);
+            *guard += 1;
+        }
+
+        assert_eq!(mutex.hooks.pins.load(Ordering::Relaxed), 5);
+        assert_eq!(mutex.hooks.unpins.load(Ordering::Relaxed), 5);
+    }
+
+    #[test]
+    fn test_lock_is_free_once_release_hook_has_run() {
+        let mutex = ReclaimingMutex::new(0u32, MockReclamation::default());
+        let mut slot_a = Slot::new();
+        let mut slot_b = Slot::new();
+
+        let guard = mutex.lock(&mut slot_a);
+        drop(guard);
+        assert_eq!(mutex.hooks.unpins.load(Ordering::Relaxed), 1);
+
+        // Dropping the guard released the inner lock and ran `on_release`; once that drop has
+        // returned the lock must be free, so this acquisition completes instead of spinning.
+        drop(mutex.lock(&mut slot_b));
+    }
+}
@@ -0,0 +1,33 @@
+//! Interop with the `spin` crate.
+//!
+//! `spin`'s lock types are generic over a `RelaxStrategy` controlling how they busy-wait.
+//! `Pause` implements that trait on top of this crate's own `pause` primitive, so a
+//! `spin::Mutex<T, Pause>` (or `spin::RwLock`, etc.) spins the same way this crate's `Mutex` does,
+//! instead of pulling in `spin`'s default `Spin` strategy.
+
+use spin::RelaxStrategy;
+
+use crate::pause::pause;
+
+/// A `spin::RelaxStrategy` backed by this crate's `pause` primitive.
+pub struct Pause;
+
+impl RelaxStrategy for Pause {
+    fn relax() {
+        pause();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Pause;
+
+    use spin::Mutex;
+
+    #[test]
+    fn test_spin_mutex_with_pause_strategy() {
+        let lock: Mutex<u32, Pause> = Mutex::new(0);
+        *lock.lock() += 1;
+        assert_eq!(*lock.lock(), 1);
+    }
+}
@@ -0,0 +1,85 @@
+//! A mutex over two disjoint fields, lockable independently.
+//!
+//! `lock_both` (see the `combined` module) locks two mutexes *together*, for the case
+//! where a critical section needs both at once. `SplitMutex` is for the opposite case: a single
+//! struct with two logically independent halves that different threads can work on
+//! concurrently, without one blocking the other, while still keeping both halves in one
+//! allocation.
+//!
+//! This is just two ordinary `Mutex`es, one per half, held next to each other; the independence
+//! comes for free from each having its own MCS queue.
+
+use crate::mutex::{Guard, Mutex, Slot};
+
+/// A guard for the `A` half of a `SplitMutex<A, B>`, returned by `lock_a`.
+pub type GuardA<'a, A> = Guard<'a, A>;
+
+/// A guard for the `B` half of a `SplitMutex<A, B>`, returned by `lock_b`.
+pub type GuardB<'a, B> = Guard<'a, B>;
+
+/// A mutex over two disjoint fields, `A` and `B`, each independently lockable.
+///
+/// Unlike a single `Mutex<(A, B)>`, locking one half never blocks a concurrent lock of the other:
+/// `lock_a` and `lock_b` go through separate MCS queues. The two halves still live in the same
+/// `SplitMutex` allocation, so this doesn't cost an extra allocation over locking them together.
+pub struct SplitMutex<A, B> {
+    a: Mutex<A>,
+    b: Mutex<B>
+}
+
+impl<A, B> SplitMutex<A, B> {
+    /// Creates a new split mutex, with both halves unlocked.
+    pub fn new(a: A, b: B) -> SplitMutex<A, B> {
+        SplitMutex { a: Mutex::new(a), b: Mutex::new(b) }
+    }
+}
+
+impl<A, B> SplitMutex<A, B> {
+    /// Locks the `A` half, blocking the current thread until it is able to do so.
+    ///
+    /// Concurrent locking of the `B` half is unaffected.
+    pub fn lock_a<'a>(&'a self, slot: &'a mut Slot) -> GuardA<'a, A> {
+        self.a.lock(slot)
+    }
+
+    /// Locks the `B` half, blocking the current thread until it is able to do so.
+    ///
+    /// Concurrent locking of the `A` half is unaffected.
+    pub fn lock_b<'a>(&'a self, slot: &'a mut Slot) -> GuardB<'a, B> {
+        self.b.lock(slot)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SplitMutex;
+    use crate::mutex::Slot;
+
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_lock_a_and_lock_b_are_independent() {
+        let split = Arc::new(SplitMutex::new(0u32, 0u32));
+        let barrier = Arc::new(Barrier::new(2));
+
+        let split1 = split.clone();
+        let barrier1 = barrier.clone();
+        let t1 = thread::spawn(move || {
+            let mut slot = Slot::new();
+            let _guard_a = split1.lock_a(&mut slot);
+            barrier1.wait();
+            thread::sleep(Duration::from_millis(100));
+        });
+
+        // Wait until `t1` is definitely holding `a`, then confirm `lock_b` doesn't wait on it.
+        barrier.wait();
+        let mut slot = Slot::new();
+        let start = Instant::now();
+        let _guard_b = split.lock_b(&mut slot);
+        assert!(start.elapsed() < Duration::from_millis(100), "lock_b blocked on lock_a being held");
+
+        t1.join().unwrap();
+    }
+}
@@ -0,0 +1,51 @@
+//! Interop for acquiring this crate's `Mutex` from async code via tokio's blocking thread pool.
+//!
+//! This is for critical sections that are short-blocking but sit behind an occasional async
+//! call site, where converting the whole call chain to the `async` feature's cooperative locking
+//! isn't worth it. `lock_blocking` offloads the actual (synchronous, spinning) acquisition to
+//! tokio's blocking pool via `spawn_blocking`, so the calling async task's executor thread is
+//! never itself blocked spinning on the MCS queue.
+//!
+//! `spawn_blocking`'s closure has no borrow of the calling task's stack frame to hold a `Guard`
+//! and `Slot` against, so this returns an `ArcMutexGuard` instead: it owns its `Arc` clone and a
+//! heap-allocated `Slot`, so it can cross the thread-pool boundary and be handed back to the
+//! calling task intact.
+
+use std::sync::Arc;
+
+use crate::arc_guard::ArcMutexGuard;
+use crate::mutex::Mutex;
+
+/// Locks `arc` on tokio's blocking thread pool, returning an owned guard once acquired.
+///
+/// # Panics
+///
+/// Panics if the spawned blocking task itself panics (which it only would if `Mutex::lock`
+/// does), or if called outside of a tokio runtime.
+pub async fn lock_blocking<T>(arc: Arc<Mutex<T>>) -> ArcMutexGuard<T>
+    where T: Send + Sync + 'static
+{
+    ::tokio::task::spawn_blocking(move || ArcMutexGuard::lock(arc))
+        .await
+        .expect("blocking lock task panicked")
+}
+
+#[cfg(test)]
+mod test {
+    use super::lock_blocking;
+    use crate::mutex::Mutex;
+
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_lock_blocking_returns_a_usable_owned_guard() {
+        let lock = Arc::new(Mutex::new(0u32));
+
+        {
+            let mut guard = lock_blocking(lock.clone()).await;
+            *guard += 1;
+        }
+
+        assert_eq!(*lock_blocking(lock.clone()).await, 1);
+    }
+}
@@ -0,0 +1,216 @@
+//! A background thread that periodically scans opted-in mutexes for holders that have been
+//! stuck longer than expected, for production deployments that want to catch an accidental
+//! deadlock or a forgotten unlock before a user reports it.
+//!
+//! Mutexes opt in explicitly by calling `Mutex::register_for_watchdog` on an `Arc`-owned mutex
+//! (the registry holds only a `Weak` reference, so registering never keeps a mutex alive past its
+//! last real owner); `spawn_watchdog` then polls every still-alive registered mutex and invokes a
+//! callback for any that have been continuously held past a configurable threshold.
+
+use core::time::Duration;
+
+use std::string::{String, ToString};
+use std::sync::{Arc, Mutex as StdMutex, OnceLock, Weak};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle, ThreadId};
+use std::time::Instant;
+use std::vec::Vec;
+
+use crate::mutex::Mutex;
+
+trait WatchdogTarget: Send + Sync {
+    fn stuck_info(&self, now: Instant) -> Option<(Duration, ThreadId, Option<String>)>;
+}
+
+impl<T: Send + Sync + 'static> WatchdogTarget for Mutex<T> {
+    fn stuck_info(&self, now: Instant) -> Option<(Duration, ThreadId, Option<String>)> {
+        let (since, holder, holder_name) = self.held_since()?;
+        Some((now.saturating_duration_since(since), holder, holder_name))
+    }
+}
+
+fn registry() -> &'static StdMutex<Vec<Weak<dyn WatchdogTarget>>> {
+    static REGISTRY: OnceLock<StdMutex<Vec<Weak<dyn WatchdogTarget>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| StdMutex::new(Vec::new()))
+}
+
+impl<T: Send + Sync + 'static> Mutex<T> {
+    /// Registers this mutex with the global watchdog registry, so any watchdog spawned via
+    /// `spawn_watchdog` (past or future) will include it in its scans.
+    ///
+    /// Takes `&Arc<Self>` rather than `&self` because the registry keeps only a `Weak` reference:
+    /// once every `Arc` to this mutex is dropped, it silently stops being scanned instead of
+    /// being kept alive forever by having once been registered.
+    pub fn register_for_watchdog(self: &Arc<Self>) {
+        let target: Arc<dyn WatchdogTarget> = self.clone();
+        registry().lock().unwrap().push(Arc::downgrade(&target));
+    }
+}
+
+/// Information about a mutex found stuck by a watchdog scan, passed to `spawn_watchdog`'s
+/// callback.
+pub struct StuckLockInfo {
+    /// How long the mutex has been continuously held so far, as of the scan that found it stuck.
+    pub held_for: Duration,
+    /// The thread currently holding the mutex.
+    pub holder: ThreadId,
+    /// The holding thread's name, if it had one set via `std::thread::Builder::name` at the
+    /// moment it acquired the mutex (Rust names the process's main thread `"main"`). Falls back
+    /// to `None` (report `holder` instead) for unnamed threads.
+    pub holder_name: Option<String>
+}
+
+/// A handle to a background thread spawned by `spawn_watchdog`.
+///
+/// Dropping this stops the watchdog thread (without blocking to join it); call `stop` explicitly
+/// to block until it has actually exited.
+pub struct WatchdogHandle {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>
+}
+
+impl WatchdogHandle {
+    /// Signals the watchdog thread to stop and blocks until it has exited.
+    pub fn stop(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for WatchdogHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Spawns a background thread that periodically scans every mutex registered via
+/// `Mutex::register_for_watchdog` and calls `on_stuck` for any currently held longer than
+/// `threshold`.
+///
+/// A mutex still held past `threshold` on a later scan is reported again on every such scan, not
+/// just the first time it crosses the threshold; `on_stuck` should be cheap or otherwise tolerant
+/// of repeated calls (e.g. rate-limiting its own alerting) if that matters to the caller.
+pub fn spawn_watchdog<F>(threshold: Duration, on_stuck: F) -> WatchdogHandle
+    where F: Fn(StuckLockInfo) + Send + 'static
+{
+    // Poll noticeably more often than the threshold so a holder that crosses it is caught
+    // promptly, but never busier than every 10ms, to keep the thread cheap when idle.
+    let poll_interval = ::core::cmp::max(threshold / 4, Duration::from_millis(10));
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_for_thread = stop.clone();
+    let thread = thread::Builder::new()
+        .name("mcs-watchdog".to_string())
+        .spawn(move || {
+            while !stop_for_thread.load(Ordering::Relaxed) {
+                let now = Instant::now();
+                registry().lock().unwrap().retain(|weak| {
+                    match weak.upgrade() {
+                        Some(target) => {
+                            if let Some((held_for, holder, holder_name)) = target.stuck_info(now) {
+                                if held_for >= threshold {
+                                    on_stuck(StuckLockInfo {
+                                        held_for: held_for,
+                                        holder: holder,
+                                        holder_name: holder_name
+                                    });
+                                }
+                            }
+                            true
+                        }
+                        None => false
+                    }
+                });
+                thread::sleep(poll_interval);
+            }
+        })
+        .expect("failed to spawn mcs-watchdog thread");
+
+    WatchdogHandle { stop: stop, thread: Some(thread) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::spawn_watchdog;
+    use crate::mutex::{Mutex, Slot};
+
+    use std::string::ToString;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_watchdog_reports_a_lock_held_past_the_threshold() {
+        let lock = Arc::new(Mutex::new(0u32));
+        lock.register_for_watchdog();
+
+        let (tx, rx) = channel();
+        let watchdog = spawn_watchdog(Duration::from_millis(30), move |info| {
+            let _ = tx.send(info.held_for);
+        });
+
+        let mut slot = Slot::new();
+        let _guard = lock.lock(&mut slot);
+
+        let held_for = rx.recv_timeout(Duration::from_secs(5)).expect("watchdog never reported the stuck lock");
+        assert!(held_for >= Duration::from_millis(30));
+
+        watchdog.stop();
+    }
+
+    #[test]
+    fn test_watchdog_reports_the_holder_thread_name() {
+        let lock = Arc::new(Mutex::new(0u32));
+        lock.register_for_watchdog();
+
+        let lock2 = lock.clone();
+        let holder = thread::Builder::new()
+            .name("held-by-me".to_string())
+            .spawn(move || {
+                let mut slot = Slot::new();
+                let _guard = lock2.lock(&mut slot);
+                thread::sleep(Duration::from_millis(200));
+            })
+            .unwrap();
+        let holder_id = holder.thread().id();
+
+        // The watchdog scans every registered mutex, including ones held by other tests running
+        // at the same time, so only reports about this test's holder thread are forwarded.
+        let (tx, rx) = channel();
+        let watchdog = spawn_watchdog(Duration::from_millis(30), move |info| {
+            if info.holder == holder_id {
+                let _ = tx.send(info.holder_name);
+            }
+        });
+
+        let holder_name = rx.recv_timeout(Duration::from_secs(5)).expect("watchdog never reported the stuck lock");
+        assert_eq!(holder_name.as_deref(), Some("held-by-me"));
+
+        watchdog.stop();
+        holder.join().unwrap();
+    }
+
+    #[test]
+    fn test_watchdog_does_not_report_a_lock_released_before_the_threshold() {
+        let lock = Arc::new(Mutex::new(0u32));
+        lock.register_for_watchdog();
+
+        let reported = Arc::new(AtomicBool::new(false));
+        let reported2 = reported.clone();
+        let watchdog = spawn_watchdog(Duration::from_secs(60), move |_info| {
+            reported2.store(true, Ordering::Relaxed);
+        });
+
+        let mut slot = Slot::new();
+        drop(lock.lock(&mut slot));
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(!reported.load(Ordering::Relaxed));
+
+        watchdog.stop();
+    }
+}
@@ -0,0 +1,8 @@
+extern crate trybuild;
+
+#[cfg(feature = "same-thread-guard")]
+#[test]
+fn guard_is_not_send() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/guard-not-send.rs");
+}
@@ -0,0 +1,9 @@
+extern crate mcs;
+
+use mcs::Guard;
+
+fn assert_send<T: Send>() {}
+
+fn main() {
+    assert_send::<Guard<'static, i32>>();
+}